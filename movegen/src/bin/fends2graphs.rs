@@ -1,12 +1,14 @@
 use std::env;
 use std::fs::OpenOptions;
 use std::io::{BufReader, BufRead};
+use log::{debug, warn};
 use movegen::board::Board;
 use movegen::graph::{boards_2_graphs, save_graphs};
 
 const DATASET_BATCH: usize = 1000;
 
 fn main() {
+    env_logger::init();
     let args: Vec<String> = env::args().collect();
     if args.len() >= 2 {
         let filename = &args[1];
@@ -37,11 +39,14 @@ fn read_dataset_file(filename: &str) {
                 if let Ok(line) = line {
                     if line.starts_with("fen ") && line_state == 0 {
                         let fen_str = line.trim_start_matches("fen ");
-                        if let Ok(_board) = Board::from_fenstr(fen_str) {
-                            println!("board {}: {}", i, fen_str);
-                            board = _board;
-                            next_player = board.player;
-                            line_state = 1;
+                        match Board::from_fenstr(fen_str) {
+                            Ok(_board) => {
+                                debug!("board {}: {}", i, fen_str);
+                                board = _board;
+                                next_player = board.player;
+                                line_state = 1;
+                            },
+                            Err(e) => warn!("skipping fen {}: {}", fen_str, e),
                         }
                     } else if line.starts_with("score ") && line_state == 1 {
                         let score_str = line.trim_start_matches("score ");
@@ -0,0 +1,23 @@
+use std::io::{self, BufRead};
+use movegen::uci::UCIEngine;
+
+/// UCI engine. Load into a GUI or match runner.
+/// Logs to stderr, set RUST_LOG=debug to see the protocol traffic
+fn main() {
+    env_logger::init();
+    let mut engine = UCIEngine::new(io::stdout());
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        match line {
+            Ok(line) => {
+                if !engine.handle(&line) {
+                    break;
+                }
+            },
+            Err(e) => {
+                eprintln!("read line error: {}", e);
+                break;
+            }
+        }
+    }
+}
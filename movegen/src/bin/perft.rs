@@ -0,0 +1,96 @@
+// Perft tools.
+// usage: perft <bench|parallel|divide|stats> [depth] [fen]
+//   bench     times perft with copy-make against make/unmake, bulk counting of the last ply,
+//             and the board stack driver behind perft::run_for
+//   parallel  node counts of every ply with the root moves shared out to a thread per core
+//   divide    node counts under each root move, to compare against another engine's
+//   stats     captures, enpassants, castles, promotions, checks and mates of every ply up to depth
+
+use std::env;
+use std::thread;
+use std::time::Instant;
+
+use movegen::board::Board;
+use movegen::perft;
+
+const USAGE: &str = "usage: perft <bench|parallel|divide|stats> [depth] [fen]";
+
+fn main() {
+    env_logger::init();
+    let mut args = env::args().skip(1);
+    let cmd = args.next();
+    let depth = args.next().and_then(|d| d.parse::<u16>().ok()).unwrap_or(5);
+    let fen: Vec<String> = args.collect();
+    let board = if fen.is_empty() {
+        Board::standard()
+    } else {
+        match Board::from_fenstr(&fen.join(" ")) {
+            Ok(board) => board,
+            Err(e) => {
+                eprintln!("{}", e);
+                return
+            }
+        }
+    };
+
+    match cmd.as_deref() {
+        Some("bench") => bench(board, depth),
+        Some("parallel") => parallel(board, depth),
+        Some("divide") => divide(board, depth),
+        Some("stats") => stats(board, depth),
+        _ => eprintln!("{}", USAGE),
+    }
+}
+
+fn bench(board: Board, depth: u16) {
+    let start = Instant::now();
+    let nodes = perft::run_for(board, depth)[&depth] as u64;
+    report("run_for", nodes, start);
+
+    let start = Instant::now();
+    let nodes = perft::count(&board, depth);
+    report("copy-make", nodes, start);
+
+    let start = Instant::now();
+    let nodes = perft::count_unmake(&mut board.clone(), depth);
+    report("make/unmake", nodes, start);
+
+    let start = Instant::now();
+    let nodes = perft::count_bulk(&board, depth);
+    report("bulk", nodes, start);
+}
+
+fn report(name: &str, nodes: u64, start: Instant) {
+    let secs = start.elapsed().as_secs_f64();
+    println!("{:12} {:>12} nodes {:>8.3}s {:>12.0} nps", name, nodes, secs, nodes as f64 / secs);
+}
+
+fn parallel(board: Board, depth: u16) {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let start = Instant::now();
+    let node_counts = perft::run_parallel(board, depth, 1, threads);
+    for d in 0..=depth {
+        println!("{:>5} {:>14}", d, node_counts.get(&d).unwrap_or(&0));
+    }
+    report(&format!("{} threads", threads), node_counts.values().sum::<usize>() as u64, start);
+}
+
+fn divide(board: Board, depth: u16) {
+    let mut total = 0;
+    for (mov, nodes) in perft::divide(&board, depth) {
+        println!("{}: {}", mov.to_uci(), nodes);
+        total += nodes;
+    }
+    println!("\nNodes searched: {}", total);
+}
+
+fn stats(board: Board, depth: u16) {
+    println!("{:>5} {:>12} {:>10} {:>8} {:>8} {:>10} {:>8} {:>8} {:>8} {:>8}",
+        "depth", "nodes", "captures", "e.p.", "castles", "promotions", "checks", "disc+", "double+", "mates");
+    for d in 0..=depth {
+        let s = perft::stats(&board, d);
+        println!("{:>5} {:>12} {:>10} {:>8} {:>8} {:>10} {:>8} {:>8} {:>8} {:>8}",
+            d, s.nodes, s.captures, s.enpassants, s.castles, s.promotions,
+            s.checks, s.discovery_checks, s.double_checks, s.checkmates);
+    }
+}
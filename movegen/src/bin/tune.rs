@@ -0,0 +1,252 @@
+// Texel tuning of the evaluation weights.
+// usage: tune <out file> <dataset file>... [--epochs n] [--params file]
+//   Dataset files hold fen, score and result records each ended by an "e" line, as in eval/gnn/data.
+//   Results are from the side to move's view. Each position is resolved to the quiet position at the
+//   end of its quiescence search, then the weights (starting from --params or the defaults) are fitted
+//   so sigmoid(eval) predicts the results, by gradient descent on the mean squared error.
+//   The tuned weights are written in the format `EvalParams::load` and the EvalFile UCI option read.
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{BufReader, BufRead};
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+use log::warn;
+
+use movegen::board::Board;
+use movegen::eval::{self, Evaluator, EvalParams, Score};
+use movegen::search::{Searcher, SearchLimits, MATE, MAX_DEPTH};
+use movegen::tt::TransTable;
+
+const USAGE: &str = "usage: tune <out file> <dataset file>... [--epochs n] [--params file]";
+const DEFAULT_EPOCHS: usize = 2000;
+// Adam step size, in centipawns
+const LEARNING_RATE: f64 = 1.0;
+const BETA1: f64 = 0.9;
+const BETA2: f64 = 0.999;
+const REPORT_EPOCHS: usize = 100;
+
+/// A quiet position as a linear function of the weights
+struct Position {
+    /// (weight index, white's count less black's) of the weights the position uses
+    coefs: Vec<(usize, f64)>,
+    /// Share of the midgame value, game phase / MAX_PHASE
+    phase: f64,
+    /// 1 for a white win, 0.5 for a draw, 0 for a black win
+    result: f64,
+}
+
+impl Position {
+    /// Centipawns from white's view for mg weights {mg} and eg weights {eg}
+    fn eval(&self, mg: &[f64], eg: &[f64]) -> f64 {
+        let (mg_sum, eg_sum) = self.coefs.iter()
+                .fold((0.0, 0.0), |(m, e), &(i, c)| (m + c * mg[i], e + c * eg[i]));
+        mg_sum * self.phase + eg_sum * (1.0 - self.phase)
+    }
+}
+
+fn main() {
+    env_logger::init();
+    let mut epochs = DEFAULT_EPOCHS;
+    let mut params = EvalParams::default();
+    let mut files = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--epochs" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => epochs = n,
+                None => return eprintln!("{}", USAGE),
+            },
+            "--params" => match args.next().map(|path| EvalParams::load(&path)) {
+                Some(Ok(loaded)) => params = loaded,
+                Some(Err(e)) => return eprintln!("can't load params: {}", e),
+                None => return eprintln!("{}", USAGE),
+            },
+            _ => files.push(arg),
+        }
+    }
+    if files.len() < 2 {
+        return eprintln!("{}", USAGE)
+    }
+    let out_file = files.remove(0);
+
+    let mut records = Vec::new();
+    for filename in files.iter() {
+        if let Err(e) = read_dataset_file(filename, &mut records) {
+            return eprintln!("can't read {}: {}", filename, e)
+        }
+    }
+    let start = Instant::now();
+    let leaves = quiet_positions(&params, &records);
+    println!("{} of {} positions resolved in {:.1}s", leaves.len(), records.len(), start.elapsed().as_secs_f64());
+    if leaves.is_empty() {
+        return eprintln!("no positions to tune on")
+    }
+
+    let start = Instant::now();
+    let positions = linear_positions(&leaves);
+    println!("weight counts of {} weights taken in {:.1}s", params.to_vec().len(), start.elapsed().as_secs_f64());
+
+    let weights = params.to_vec();
+    let mut mg: Vec<f64> = weights.iter().map(|w| w.mg as f64).collect();
+    let mut eg: Vec<f64> = weights.iter().map(|w| w.eg as f64).collect();
+    let k = fit_k(&positions, &mg, &eg);
+    let start_error = error(&positions, &mg, &eg, k);
+    println!("K = {:.3}, error {:.6}", k, start_error);
+
+    tune(&positions, &mut mg, &mut eg, k, epochs);
+
+    let tuned: Vec<Score> = mg.iter().zip(eg.iter())
+            .map(|(mg, eg)| Score::new(mg.round() as i32, eg.round() as i32))
+            .collect();
+    params.set_from(&tuned);
+    // the error of the rounded weights through the evaluator itself
+    let evaluator = Evaluator::new(params.clone());
+    let end_error = leaves.iter()
+            .map(|(board, result)| {
+                let trace = evaluator.trace(board);
+                (result - sigmoid(trace.total().taper(trace.phase) as f64, k)).powi(2)
+            })
+            .sum::<f64>() / leaves.len() as f64;
+    println!("tuned error {:.6}", end_error);
+
+    let header = format!(
+        "# tuned on {} positions, K = {:.3}, error {:.6} -> {:.6}\n", leaves.len(), k, start_error, end_error
+    );
+    if let Err(e) = fs::write(&out_file, header + &params.to_string()) {
+        eprintln!("can't write {}: {}", out_file, e);
+    }
+}
+
+/// Add the positions and results in {filename} to {records}, results from white's view
+fn read_dataset_file(filename: &str, records: &mut Vec<(Board, f64)>) -> std::io::Result<()> {
+    let file_in = OpenOptions::new().read(true).open(filename)?;
+    let mut board = None;
+    let mut result = None;
+    for line in BufReader::new(file_in).lines() {
+        let line = line?;
+        if let Some(fen_str) = line.strip_prefix("fen ") {
+            board = match Board::from_fenstr(fen_str) {
+                Ok(board) => Some(board),
+                Err(e) => {
+                    warn!("skipping fen {}: {}", fen_str, e);
+                    None
+                },
+            };
+        } else if let Some(result_str) = line.strip_prefix("result ") {
+            result = result_str.trim().parse::<i8>().ok();
+        } else if line.starts_with('e') {
+            if let (Some(board), Some(result)) = (board.take(), result.take()) {
+                let result = if board.player { result } else { -result };
+                records.push((board, (result as f64 + 1.0) / 2.0));
+            }
+        }
+    }
+    Ok( () )
+}
+
+/// The quiet positions at the end of the quiescence search of each record, leaving out mates
+fn quiet_positions(params: &EvalParams, records: &[(Board, f64)]) -> Vec<(Board, f64)> {
+    let mut searcher = Searcher::new(SearchLimits::default(), Arc::new(AtomicBool::new(false)), TransTable::new(0));
+    searcher.set_evaluator(Evaluator::new(params.clone()));
+    records.iter()
+            .filter_map(|(board, result)| {
+                let (score, pv) = searcher.quiesce(board);
+                if score.abs() > MATE - MAX_DEPTH as i32 * 2 {
+                    return None
+                }
+                let leaf = pv.iter().try_fold(*board, |board, mov| board.make_move(mov))?;
+                Some((leaf, *result))
+            })
+            .collect()
+}
+
+/// The evaluation is a sum of weights times piece and square counts, the same counts for mg and eg.
+/// Each weight's count is what the evaluation comes to with that weight at 1 and the rest at 0
+fn linear_positions(leaves: &[(Board, f64)]) -> Vec<Position> {
+    let mut zero = EvalParams::default();
+    let n_weights = zero.to_vec().len();
+    let mut unit = vec![Score::default(); n_weights];
+    zero.set_from(&unit);
+    let mut evaluator = Evaluator::new(zero);
+
+    let mut positions: Vec<Position> = leaves.iter()
+            .map(|(board, result)| Position{
+                coefs: Vec::new(),
+                phase: eval::game_phase(board) as f64 / eval::MAX_PHASE as f64,
+                result: *result,
+            })
+            .collect();
+    for i in 0..n_weights {
+        unit[i] = Score::new(1, 0);
+        let mut params = evaluator.params().clone();
+        params.set_from(&unit);
+        evaluator.set_params(params);
+        for (position, (board, _)) in positions.iter_mut().zip(leaves.iter()) {
+            let count = evaluator.trace(board).total().mg;
+            if count != 0 {
+                position.coefs.push((i, count as f64));
+            }
+        }
+        unit[i] = Score::default();
+    }
+    positions
+}
+
+/// Expected result from white's view of a white view eval {cp}
+fn sigmoid(cp: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * cp / 400.0))
+}
+
+fn error(positions: &[Position], mg: &[f64], eg: &[f64], k: f64) -> f64 {
+    positions.iter()
+            .map(|pos| (pos.result - sigmoid(pos.eval(mg, eg), k)).powi(2))
+            .sum::<f64>() / positions.len() as f64
+}
+
+/// The sigmoid scale that best fits the starting weights, by ternary search
+fn fit_k(positions: &[Position], mg: &[f64], eg: &[f64]) -> f64 {
+    let (mut lo, mut hi) = (0.0, 5.0);
+    for _ in 0..60 {
+        let third = (hi - lo) / 3.0;
+        if error(positions, mg, eg, lo + third) < error(positions, mg, eg, hi - third) {
+            hi -= third;
+        } else {
+            lo += third;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+/// Full batch gradient descent with Adam on the mean squared error
+fn tune(positions: &[Position], mg: &mut [f64], eg: &mut [f64], k: f64, epochs: usize) {
+    let n = mg.len();
+    let (mut m, mut v) = (vec![0.0; 2*n], vec![0.0; 2*n]);
+    let mut grad = vec![0.0; 2*n];
+    for epoch in 1..=epochs {
+        grad.iter_mut().for_each(|g| *g = 0.0);
+        for pos in positions.iter() {
+            let s = sigmoid(pos.eval(mg, eg), k);
+            // d (result - s)^2 / d eval
+            let d_eval = 2.0 * (s - pos.result) * s * (1.0 - s) * k * 10f64.ln() / 400.0;
+            for &(i, c) in pos.coefs.iter() {
+                grad[i] += d_eval * c * pos.phase;
+                grad[n + i] += d_eval * c * (1.0 - pos.phase);
+            }
+        }
+        let scale = 1.0 / positions.len() as f64;
+        for i in 0..2*n {
+            let g = grad[i] * scale;
+            m[i] = BETA1 * m[i] + (1.0 - BETA1) * g;
+            v[i] = BETA2 * v[i] + (1.0 - BETA2) * g * g;
+            let m_hat = m[i] / (1.0 - BETA1.powi(epoch as i32));
+            let v_hat = v[i] / (1.0 - BETA2.powi(epoch as i32));
+            let step = LEARNING_RATE * m_hat / (v_hat.sqrt() + 1e-8);
+            if i < n { mg[i] -= step } else { eg[i - n] -= step }
+        }
+        if epoch % REPORT_EPOCHS == 0 || epoch == epochs {
+            println!("epoch {} error {:.6}", epoch, error(positions, mg, eg, k));
+        }
+    }
+}
@@ -2,8 +2,11 @@ use fnv::FnvHashMap;
 
 use crate::pieces::{Piece, PieceType, WHITE, BLACK};
 use crate::pieces::std_pieces::*;
-use crate::moves::{MoveList, Move, MoveMeta, BitPositions};
+use crate::moves::{MoveList, Move, MoveMeta, BitPositions, GenType};
 use crate::utils;
+use crate::error::{FenError, MoveParseError};
+use regex::Regex;
+use crate::zobrist;
 
 
 pub mod rank_bb {
@@ -50,7 +53,7 @@ impl Direction {
 pub type BitBoard = u64;
 pub type Square = u8;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub struct Board {
     pub bitboards: [BitBoard; STD_PIECECOUNT],
     pub castle_w_s: bool,
@@ -60,7 +63,28 @@ pub struct Board {
     pub player: bool,
     pub fifty_move_count: u8,
     pub half_move_count: u16,
-    pub enp_target: u8
+    pub enp_target: u8,
+    /// Zobrist key of the position, updated incrementally by `Board::make_move`
+    pub hash: u64,
+}
+
+/// What a move changed that can't be worked out from the move itself.
+/// Returned by `Board::make_move_inplace` for `Board::unmake_move`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Undo {
+    pub captured: Option<Piece>,
+    // castling rights packed as white short, white long, black short, black long
+    castle_rights: u8,
+    pub enp_target: u8,
+    pub fifty_move_count: u8,
+    pub hash: u64,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum BoardStatus {
+    Ongoing,
+    Checkmate,
+    Stalemate,
 }
 
 #[derive(Copy, Clone)]
@@ -70,11 +94,12 @@ pub struct BoardState<'a> {
     pub pinned_pieces:   &'a FnvHashMap<u8, BitBoard>,
     pub opp_check_mask:  &'a BitBoard,
     pub opp_attack_mask: &'a BitBoard,
+    pub gen_type:        GenType,
 }
 
 impl<'a> Board {
     pub fn standard() -> Board {
-        Board {
+        let mut board = Board {
             bitboards: STD_BITBOARDS,
             castle_w_s: true,
             castle_w_l: true,
@@ -84,10 +109,13 @@ impl<'a> Board {
             fifty_move_count: 0,
             half_move_count: 0,
             enp_target: 0,
-        }
+            hash: 0,
+        };
+        board.hash = board.compute_hash();
+        board
     }
     pub fn empty() -> Board {
-        Board {
+        let mut board = Board {
             bitboards: [0; STD_PIECECOUNT],
             castle_w_s: true,
             castle_w_l: true,
@@ -97,11 +125,14 @@ impl<'a> Board {
             fifty_move_count: 0,
             half_move_count: 0,
             enp_target: 0,
-        }
+            hash: 0,
+        };
+        board.hash = board.compute_hash();
+        board
     }
-    /// Parse an FEN string position into a board
-    pub fn from_fenstr(fen_str: &str)-> Result<Board, String> {
-        // TODO: add 50move rule count and half_move_count count to the Board struct
+    /// Parse an FEN string position into a board.
+    /// The position is validated, see `Board::validate`
+    pub fn from_fenstr(fen_str: &str)-> Result<Board, FenError> {
         let mut board = Board::empty();
 
         // board from 8th rank <space>
@@ -112,16 +143,21 @@ impl<'a> Board {
         // full_move_count count
         // 
         // ex. rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1
+        // the clocks are optional and default to "0 1"
+        let mut full_move_count = 1;
+        let mut section_count = 0;
         for (i, section_str) in fen_str.split_whitespace().enumerate() {
+            section_count += 1;
             match i {
                 0 => {
                     let rank_strings: Vec<&str> = section_str.split('/').collect();
                     if rank_strings.len() != 8 {
-                        return Err( "Piece placement string not complete".to_string() );
+                        return Err( FenError::RankCount(rank_strings.len()) );
                     }
                     
-                    for (rank, &rank_str) in rank_strings.iter().enumerate() {
-                        let rank = rank as u8;
+                    // FEN lists the 8th rank first
+                    for (i, &rank_str) in rank_strings.iter().enumerate() {
+                        let rank = 7 - i as u8;
                         let mut file = 0u8;
                         for piece_char in rank_str.chars() {
                             let player = "PNBRQK".contains(piece_char);
@@ -134,55 +170,205 @@ impl<'a> Board {
                                 'q' | 'Q' => piece.piece_type = PieceType::Queen, 
                                 'k' | 'K' => piece.piece_type = PieceType::King, 
                                 '1'..='8' => {
-                                    if let Some(space_count) = piece_char.to_digit(10) {
-                                        file += space_count as u8;
-                                        continue;
-                                    } else {
-                                        return Err( format!("Something went wrong while reading {} as a number", piece_char) )
-                                    }
+                                    file += piece_char as u8 - b'0';
+                                    continue;
                                 }, 
                                 _ => {
-                                    return Err( format!("Piece placement string contains invalid character: {}", piece_char) )
+                                    return Err( FenError::InvalidPieceChar(piece_char) )
                                 }
                             }
+                            if file >= 8 {
+                                return Err( FenError::RankLength(rank + 1) );
+                            }
                             board.bitboards[get_piece_i(&piece)] |= utils::pos_mask(rank*8 + file);
                             file += 1
                         }
+                        if file != 8 {
+                            return Err( FenError::RankLength(rank + 1) );
+                        }
                     }
                 },
                 1 => {
-                    board.player = section_str == "w";
+                    board.player = match section_str {
+                        "w" => WHITE,
+                        "b" => BLACK,
+                        _ => return Err( FenError::InvalidPlayer(section_str.to_string()) ),
+                    };
                 },
                 2 => {
-                    board.castle_w_s = section_str.contains('K');
-                    board.castle_w_l = section_str.contains('Q');
-                    board.castle_b_s = section_str.contains('k');
-                    board.castle_b_l = section_str.contains('q');
+                    board.castle_w_s = false;
+                    board.castle_w_l = false;
+                    board.castle_b_s = false;
+                    board.castle_b_l = false;
+                    if section_str != "-" {
+                        for castle_char in section_str.chars() {
+                            let castle_right = match castle_char {
+                                'K' => &mut board.castle_w_s,
+                                'Q' => &mut board.castle_w_l,
+                                'k' => &mut board.castle_b_s,
+                                'q' => &mut board.castle_b_l,
+                                _ => return Err( FenError::InvalidCastling(section_str.to_string()) ),
+                            };
+                            // each right may only be listed once
+                            if *castle_right {
+                                return Err( FenError::InvalidCastling(section_str.to_string()) );
+                            }
+                            *castle_right = true;
+                        }
+                    }
                 },
                 3 => {
-                    if let Some(dest) = utils::pos_from_str(section_str) {
-                        board.enp_target = dest;
+                    // FEN gives the square behind the pushed pawn, the board tracks the pawn itself
+                    if section_str != "-" {
+                        let target = utils::pos_from_str(section_str)
+                                        .filter(|_| section_str.len() == 2)
+                                        .ok_or_else(|| FenError::InvalidEnpassant(section_str.to_string()))?;
+                        let (_, rank) = utils::file_rank(target);
+                        // the target is on the 6th rank if black just pushed, the 3rd if white did
+                        if rank != if board.player { 5 } else { 2 } {
+                            return Err( FenError::BogusEnpassant(target) );
+                        }
+                        board.enp_target = if board.player { target - 8 } else { target + 8 };
                     }
                 },
                 4 => {
-                    if let Ok(count) = section_str.parse::<u8>() {
-                        board.fifty_move_count = count;
-                    }
+                    board.fifty_move_count = section_str.parse::<u8>()
+                        .map_err(|_| FenError::InvalidHalfmoveClock(section_str.to_string()))?;
                 },
                 5 => {
-                    if let Ok(count) = section_str.parse::<u16>() {
-                        board.half_move_count = count*2;
-                    }
+                    full_move_count = section_str.parse::<u16>().ok()
+                        .filter(|count| *count > 0)
+                        .ok_or_else(|| FenError::InvalidFullmoveNumber(section_str.to_string()))?;
                 },
                 _ => {
-                    return Err( "Found more segments than expected".to_string() );
+                    return Err( FenError::ExtraSection );
                 }
             }
         }
+        if section_count < 4 {
+            return Err( FenError::MissingSection );
+        }
+        // plies played since the start of the game, the fullmove number increments after black moves.
+        // u16::MAX and up would overflow on the next move
+        board.half_move_count = (full_move_count - 1).checked_mul(2)
+            .and_then(|count| count.checked_add((!board.player) as u16))
+            .filter(|&count| count < u16::MAX)
+            .ok_or_else(|| FenError::InvalidFullmoveNumber(full_move_count.to_string()))?;
+        board.hash = board.compute_hash();
+        board.validate()?;
         Ok( board )
     }
+    /// Check that the position could occur in a game:
+    /// one king each, no pawns on the back ranks, the player not to move not in check,
+    /// castling rights backed by the king and rook on their starting squares
+    /// and an enpassant target behind a pawn that could have just double pushed
+    pub fn validate(&self) -> Result<(), FenError> {
+        for &player in &[WHITE, BLACK] {
+            let count = utils::n_set_bits(self.piece_bb(PieceType::King, player));
+            if count != 1 {
+                return Err( FenError::KingCount{ player, count } );
+            }
+        }
+
+        let pawns = self.piece_bb(PieceType::Pawn, WHITE) | self.piece_bb(PieceType::Pawn, BLACK);
+        if let Some(pos) = BitPositions(pawns & (rank_bb::ONE | rank_bb::EIGHT)).next() {
+            return Err( FenError::PawnOnBackRank(pos) );
+        }
+
+        for &player in &[WHITE, BLACK] {
+            for &is_short in &[true, false] {
+                if self.castle_rights(player, is_short) && (
+                    self.piece_bb(PieceType::King, player) & utils::king_home(player) == 0 ||
+                    self.piece_bb(PieceType::Rook, player) & utils::rook_home(player, is_short) == 0
+                ) {
+                    return Err( FenError::CastlingRights{ player, is_short } );
+                }
+            }
+        }
+
+        if self.enp_target != 0 {
+            // the pushed pawn is in place and both the square it skipped and the one it came from are empty
+            let opp = !self.player;
+            let target = if self.player { self.enp_target + 8 } else { self.enp_target - 8 };
+            let src = if self.player { self.enp_target + 16 } else { self.enp_target - 16 };
+            let empty = self.empty_mask();
+            if self.piece_bb(PieceType::Pawn, opp) & utils::pos_mask(self.enp_target) == 0 ||
+                empty & utils::pos_mask(target) == 0 ||
+                empty & utils::pos_mask(src) == 0 {
+                return Err( FenError::BogusEnpassant(target) );
+            }
+        }
+
+        let opp_king_pos = self.piece_bb(PieceType::King, !self.player).trailing_zeros() as u8;
+        if self.sq_attacked(opp_king_pos, !self.player) {
+            return Err( FenError::OppInCheck );
+        }
+        Ok( () )
+    }
+    /// Serialize the board into an FEN string. Inverse of `Board::from_fenstr`
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::with_capacity(90);
+
+        // piece placement from the 8th rank
+        for rank in (0..8u8).rev() {
+            let mut space_count = 0;
+            for file in 0..8u8 {
+                match self.piece_at(rank*8 + file) {
+                    Some(piece) => {
+                        if space_count > 0 {
+                            fen.push_str(&space_count.to_string());
+                            space_count = 0;
+                        }
+                        fen.push(piece.piece_char());
+                    },
+                    None => space_count += 1,
+                }
+            }
+            if space_count > 0 {
+                fen.push_str(&space_count.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        // next player
+        fen.push_str(if self.player { " w " } else { " b " });
+
+        // castling rights
+        let castle_len = fen.len();
+        if self.castle_w_s { fen.push('K') }
+        if self.castle_w_l { fen.push('Q') }
+        if self.castle_b_s { fen.push('k') }
+        if self.castle_b_l { fen.push('q') }
+        if fen.len() == castle_len {
+            fen.push('-');
+        }
+
+        // enpassant target sq, the square the double pushed pawn skipped over
+        if self.enp_target != 0 {
+            let target = if self.player { self.enp_target + 8 } else { self.enp_target - 8 };
+            fen.push(' ');
+            fen.push_str(&utils::file_rank_str(target));
+        } else {
+            fen.push_str(" -");
+        }
+
+        // 50 move rule count and full move count
+        let full_move_count = self.half_move_count/2 + 1;
+        fen.push_str(&format!(" {} {}", self.fifty_move_count, full_move_count));
+        fen
+    }
+    /// Zobrist key of the position computed from scratch. Should always equal the incrementally updated `hash`
+    pub fn compute_hash(&self) -> u64 {
+        zobrist::hash(self)
+    }
+    /// Returns the squares {player} attacks and the check mask: the rays from the opponent's king
+    /// to each checking piece, checkers included. 
+    /// The opponent's king doesn't block attacks, so it can't step back along a checking ray
     pub fn attack_check_mask(&self, player: bool) -> (BitBoard, BitBoard) {
         let opp_king_mask = self.piece_bb(PieceType::King, !player);
+        let empty = self.empty_mask() | opp_king_mask;
 
         let mut attack_mask = 0;
         let mut check_mask = 0;
@@ -191,7 +377,7 @@ impl<'a> Board {
                                     .enumerate()
                                     .filter(|(i, _)| get_piece(*i).player == player) 
         {
-            let (a_mask, c_mask) = get_piece(i).attack_check_mask(bb, &self.empty_mask(), &opp_king_mask);
+            let (a_mask, c_mask) = get_piece(i).attack_check_mask(bb, &empty, &opp_king_mask);
             attack_mask |= a_mask;
             check_mask |= c_mask;
         }
@@ -211,6 +397,7 @@ impl<'a> Board {
             pinned_pieces: &pinned_pieces,
             opp_check_mask: &opp_check_mask,
             opp_attack_mask: &opp_attack_mask,
+            gen_type: GenType::All,
         };
         self.bitboards.iter()
                 .enumerate()
@@ -218,9 +405,118 @@ impl<'a> Board {
                 .for_each(|(i, bb)| ( get_piece(i).pseudo_move_list(bb, &board_state, move_list) ) );
         pinned_mask
     }
+    /// Find the legal move written in UCI long algebraic notation. ex. e2e4, e7e8q, e1g1
+    pub fn parse_uci_move(&self, move_str: &str) -> Result<Move, MoveParseError> {
+        let malformed = || MoveParseError::Malformed(move_str.to_string());
+        if !(move_str.len() == 4 || move_str.len() == 5) || !move_str.is_ascii() {
+            return Err( malformed() );
+        }
+        utils::pos_from_str(&move_str[0..2]).ok_or_else(malformed)?;
+        utils::pos_from_str(&move_str[2..4]).ok_or_else(malformed)?;
+        if move_str.len() == 5 && !"nbrq".contains(&move_str[4..]) {
+            return Err( malformed() );
+        }
+
+        let mut move_list = MoveList::new();
+        self.move_list(self.player, &mut move_list);
+        let found = move_list.iter()
+                .find(|mov| mov.to_uci() == move_str)
+                .copied();
+        found.ok_or_else(|| MoveParseError::Illegal(move_str.to_string()))
+    }
+    /// Find the legal move written in Standard Algebraic Notation. ex. Nbd7, exd5, e8=Q+, O-O
+    pub fn parse_san(&self, san: &str) -> Result<Move, MoveParseError> {
+        lazy_static! {
+            static ref PAT_SAN: Regex = Regex::new(
+                r"^([NBRQK])?([a-h])?([1-8])?x?([a-h][1-8])(?:=?([NBRQ]))?$"
+            ).unwrap();
+        }
+        // check/mate and annotation suffixes don't identify the move
+        let move_str = san.trim_end_matches(|c| "+#!?".contains(c));
+
+        let mut move_list = MoveList::new();
+        self.move_list(self.player, &mut move_list);
+        let legal_moves = move_list.iter();
+
+        let mut candidates: Vec<Move> = match move_str {
+            "O-O" | "0-0" => legal_moves
+                    .filter(|mov| mov.move_meta() == MoveMeta::Castle{ is_short: true })
+                    .copied().collect(),
+            "O-O-O" | "0-0-0" => legal_moves
+                    .filter(|mov| mov.move_meta() == MoveMeta::Castle{ is_short: false })
+                    .copied().collect(),
+            _ => {
+                let caps = PAT_SAN.captures(move_str)
+                                .ok_or_else(|| MoveParseError::Malformed(san.to_string()))?;
+                let piece_type = match caps.get(1).map(|m| m.as_str()) {
+                    Some("N") => PieceType::Knight,
+                    Some("B") => PieceType::Bishop,
+                    Some("R") => PieceType::Rook,
+                    Some("Q") => PieceType::Queen,
+                    Some("K") => PieceType::King,
+                    _ => PieceType::Pawn,
+                };
+                let src_file = caps.get(2).map(|m| m.as_str().as_bytes()[0] - b'a');
+                let src_rank = caps.get(3).map(|m| m.as_str().as_bytes()[0] - b'1');
+                let dest = utils::pos_from_str(&caps[4]).ok_or_else(|| MoveParseError::Malformed(san.to_string()))?;
+                let promo_type = caps.get(5).map(|m| match m.as_str() {
+                    "N" => PieceType::Knight,
+                    "B" => PieceType::Bishop,
+                    "R" => PieceType::Rook,
+                    _ => PieceType::Queen,
+                });
+                legal_moves
+                    .filter(|mov| mov.piece().piece_type == piece_type && mov.dest() == dest)
+                    .filter(|mov| {
+                        let (file, rank) = utils::file_rank(mov.src());
+                        src_file.unwrap_or(file) == file && src_rank.unwrap_or(rank) == rank
+                    })
+                    .filter(|mov| match mov.move_meta() {
+                        MoveMeta::Promotion{ is_capture: _, piece_type } => promo_type == Some(piece_type),
+                        _ => promo_type.is_none(),
+                    })
+                    .copied().collect()
+            }
+        };
+        match candidates.len() {
+            0 => Err( MoveParseError::Illegal(san.to_string()) ),
+            1 => Ok( candidates.remove(0) ),
+            _ => Err( MoveParseError::Ambiguous(san.to_string()) ),
+        }
+    }
+    /// Opponent pieces giving check to the player to move
+    pub fn checkers(&self) -> BitBoard {
+        let (_, check_mask) = self.attack_check_mask(!self.player);
+        check_mask & self.player_mask(!self.player)
+    }
+    pub fn in_check(&self) -> bool {
+        self.checkers() > 0
+    }
+    /// Whether the game goes on or the player to move is checkmated/stalemated
+    pub fn status(&self) -> BoardStatus {
+        let mut move_list = MoveList::new();
+        self.move_list(self.player, &mut move_list);
+        if !move_list.is_empty() {
+            BoardStatus::Ongoing
+        } else if self.in_check() {
+            BoardStatus::Checkmate
+        } else {
+            BoardStatus::Stalemate
+        }
+    }
     /// Generates all legal moves for {player} into {move_list}
     pub fn move_list(&self, player: bool, move_list: &mut MoveList) {
+        self.gen_move_list(player, GenType::All, move_list)
+    }
+    /// Generates the legal captures, enpassant captures and promotions for {player} into {move_list}
+    pub fn capture_list(&self, player: bool, move_list: &mut MoveList) {
+        self.gen_move_list(player, GenType::Captures, move_list)
+    }
+    /// Generates the legal moves of {gen_type} for {player} into {move_list}
+    pub fn gen_move_list(&self, player: bool, gen_type: GenType, move_list: &mut MoveList) {
         let (opp_attack_mask, opp_check_mask) = self.attack_check_mask(!player);
+        // in double check only the king can move
+        let double_check = utils::n_set_bits(opp_check_mask & self.player_mask(!player)) > 1;
         let mut pinned_pieces: FnvHashMap<u8, BitBoard> = FnvHashMap::default();
         let pinned_mask = self.pinned(player, &mut pinned_pieces);
         let board_state = BoardState{
@@ -229,15 +525,24 @@ impl<'a> Board {
             pinned_pieces: &pinned_pieces,
             opp_check_mask: &opp_check_mask,
             opp_attack_mask: &opp_attack_mask,
+            gen_type,
         };
         self.bitboards.iter()
                 .enumerate()
                 .filter(|(i, _)| get_piece(*i).player == player)
+                .filter(|(i, _)| !double_check || get_piece(*i).piece_type == PieceType::King)
                 .for_each(|(i, bb)| ( get_piece(i).move_list(bb, &board_state, move_list) ) );
     }
     pub fn piece_mask(&self, piece: &Piece) -> BitBoard {
         *self.bitboards.get(get_piece_i(piece)).unwrap_or(&0)
     }
+    /// The piece occupying square *pos, if any
+    pub fn piece_at(&self, pos: u8) -> Option<Piece> {
+        let pos_mask = utils::pos_mask(pos);
+        self.bitboards.iter()
+                .position(|bb| bb & pos_mask > 0)
+                .map(get_piece)
+    }
     pub fn empty_mask(&self) -> BitBoard {
         !self.bitboards.iter().fold(0, |acc, v| (acc | v) )
     }
@@ -257,29 +562,121 @@ impl<'a> Board {
     /// Checks if a square *pos (assumed to be occupied by *player) is attacked. 
     /// Not to be used for pawn squares as it doesn't consider enpassant
     pub fn sq_attacked(&self, pos: u8, player: bool) -> bool {
-        let full_occp = !self.empty_mask();
-        if let [p, n, b, r, q, k] = self.player_bbs(!player) {
-            let a_mask = ( utils::pawn_attack(pos, player) & p) |
-                            ( utils::knight_attack(pos) & n ) |
-                            ( utils::bishop_attack(pos, full_occp) & (b | q) ) |
-                            ( utils::rook_attack(pos, full_occp) & (r | q) ) |
-                            ( utils::king_attack(pos) & k );
-            a_mask > 0
-        } else {
-            false
+        self.attackers_to(pos, !self.empty_mask()) & self.player_mask(!player) > 0
+    }
+    /// Pieces of both players attacking {pos}, with sliders blocked by {occupancy}.
+    /// Pieces missing from {occupancy} are still returned, mask them out if they were removed
+    pub fn attackers_to(&self, pos: Square, occupancy: BitBoard) -> BitBoard {
+        let bishops = self.piece_bb(PieceType::Bishop, WHITE) | self.piece_bb(PieceType::Bishop, BLACK);
+        let rooks = self.piece_bb(PieceType::Rook, WHITE) | self.piece_bb(PieceType::Rook, BLACK);
+        let queens = self.piece_bb(PieceType::Queen, WHITE) | self.piece_bb(PieceType::Queen, BLACK);
+        let knights = self.piece_bb(PieceType::Knight, WHITE) | self.piece_bb(PieceType::Knight, BLACK);
+        let kings = self.piece_bb(PieceType::King, WHITE) | self.piece_bb(PieceType::King, BLACK);
+        ( utils::pawn_attack(pos, BLACK) & self.piece_bb(PieceType::Pawn, WHITE) ) |
+        ( utils::pawn_attack(pos, WHITE) & self.piece_bb(PieceType::Pawn, BLACK) ) |
+        ( utils::knight_attack(pos) & knights ) |
+        ( utils::bishop_attack(pos, occupancy) & (bishops | queens) ) |
+        ( utils::rook_attack(pos, occupancy) & (rooks | queens) ) |
+        ( utils::king_attack(pos) & kings )
+    }
+    /// Static exchange evaluation. The material {mov} wins, in centipawns, if both players
+    /// keep recapturing on its destination with their least valuable attacker while it pays.
+    /// Pieces behind a capturer join in as it leaves. Pins are ignored
+    pub fn see(&self, mov: &Move) -> i32 {
+        let meta = mov.move_meta();
+        let (src, dest) = (mov.src(), mov.dest());
+        let mut occupancy = !self.empty_mask() & !utils::pos_mask(src);
+        let mut gain = [0; 32];
+        // value of the piece standing on dest, to be captured next
+        let mut on_dest = see_value(mov.piece().piece_type);
+        match meta {
+            MoveMeta::Castle{ .. } => return 0,
+            MoveMeta::Enpassant => {
+                gain[0] = PieceType::Pawn.cp_value();
+                occupancy &= !utils::pos_mask(self.enp_target);
+            },
+            MoveMeta::Promotion{ is_capture, piece_type } => {
+                gain[0] = piece_type.cp_value() - PieceType::Pawn.cp_value();
+                if is_capture {
+                    gain[0] += self.piece_at(dest).map_or(0, |p| p.piece_type.cp_value());
+                }
+                on_dest = piece_type.cp_value();
+            },
+            _ => gain[0] = self.piece_at(dest).map_or(0, |p| p.piece_type.cp_value()),
+        }
+
+        let mut attackers = self.attackers_to(dest, occupancy) & occupancy;
+        let mut player = !self.player;
+        let mut depth = 0;
+        loop {
+            // least valuable attacker of the player to capture
+            let capturer = [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King]
+                    .iter()
+                    .map(|&piece_type| (piece_type, attackers & self.piece_bb(piece_type, player)))
+                    .find(|(_, bb)| *bb > 0);
+            let (piece_type, bb) = match capturer {
+                Some(capturer) => capturer,
+                None => break,
+            };
+            depth += 1;
+            // what {player} is up if the exchange stops after this capture
+            gain[depth] = on_dest - gain[depth-1];
+            if depth == gain.len() - 1 {
+                break;
+            }
+            occupancy &= !(bb & bb.wrapping_neg());
+            // also uncovers sliders behind the capturer
+            attackers = self.attackers_to(dest, occupancy) & occupancy;
+            on_dest = see_value(piece_type);
+            player = !player;
+        }
+        // each player stops capturing when continuing would lose more
+        while depth > 0 {
+            gain[depth-1] = -(-gain[depth-1]).max(gain[depth]);
+            depth -= 1;
         }
+        gain[0]
     }
-    /// Checks corner case of king in check along 4th rank after enpassant capture
-    pub fn is_post_enp_checked(&self, player: bool) -> bool {
-        let full_occp = !self.empty_mask();
-        let mut checked = false;
+    /// The static exchange evaluation of {mov} is at least {threshold}
+    pub fn see_ge(&self, mov: &Move, threshold: i32) -> bool {
+        self.see(mov) >= threshold
+    }
+    /// {player}'s pawns whose enpassant capture would leave their king in check from a rook or queen.
+    /// Taking both pawns off the 4th/5th rank can uncover an attack no pin ray accounts for
+    pub fn enp_pinned(&self, player: bool) -> BitBoard {
+        if self.enp_target == 0 {
+            return 0
+        }
+        let captured_mask = utils::pos_mask(self.enp_target);
+        let dest_mask = utils::pos_mask(if player { self.enp_target + 8 } else { self.enp_target - 8 });
+        let capturers = (utils::slide(captured_mask, 1, &Direction::E) | utils::slide(captured_mask, 1, &Direction::W)) &
+                self.piece_bb(PieceType::Pawn, player);
+        let sliders = self.piece_bb(PieceType::Rook, !player) | self.piece_bb(PieceType::Queen, !player);
+        let mut pinned = 0;
         for king_pos in BitPositions( self.piece_bb(PieceType::King, player) ) {
-            if let [_, _, _, r, q, _] = self.player_bbs(!player) {
-                let a_mask = utils::rook_attack(king_pos, full_occp) & (r | q);
-                checked |= a_mask > 0;
+            for src in BitPositions(capturers) {
+                let occupancy = (!self.empty_mask() ^ utils::pos_mask(src) ^ captured_mask) | dest_mask;
+                if utils::rook_attack(king_pos, occupancy) & sliders > 0 {
+                    pinned |= utils::pos_mask(src);
+                }
             }
         }
-        checked
+        pinned
+    }
+    /// Neither player can checkmate: bare kings, a single minor piece,
+    /// or only bishops that are all on the same square colour
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy = [PieceType::Pawn, PieceType::Rook, PieceType::Queen].iter()
+                .fold(0, |acc, &pt| acc | self.piece_bb(pt, WHITE) | self.piece_bb(pt, BLACK));
+        if heavy > 0 {
+            return false
+        }
+        let knights = self.piece_bb(PieceType::Knight, WHITE) | self.piece_bb(PieceType::Knight, BLACK);
+        let bishops = self.piece_bb(PieceType::Bishop, WHITE) | self.piece_bb(PieceType::Bishop, BLACK);
+        if utils::n_set_bits(knights | bishops) <= 1 {
+            return true
+        }
+        knights == 0 && (bishops & utils::DARK_SQUARES == 0 || bishops & !utils::DARK_SQUARES == 0)
     }
     pub fn castle_rights(&self, player: bool, is_short: bool) -> bool {
         match (player, is_short) {
@@ -308,82 +705,169 @@ impl<'a> Board {
             fifty_move_count: self.fifty_move_count,
             half_move_count: self.half_move_count + 1,  // increment half move count
             enp_target: 0,
+            hash: self.hash,
         }
     }
+    /// The position after {mov}, or None for `Move::invalid()`.
+    /// Legality isn't checked, {mov} is expected to come from `Board::move_list`
     pub fn make_move(&self, mov: &Move) -> Option<Board> {
+        let mut next_board = *self;
+        next_board.make_move_inplace(mov)?;
+        Some( next_board )
+    }
+    /// Play {mov} on this board. Returns what `Board::unmake_move` needs to take it back,
+    /// or None, leaving the board untouched, for `Move::invalid()`. Legality isn't checked
+    pub fn make_move_inplace(&mut self, mov: &Move) -> Option<Undo> {
         if mov.is_invalid() {
             return None
         }
 
         let piece = mov.piece();
         let player = piece.player;
-        let mut bitboards = self.bitboards.clone();
+        let piece_i = get_piece_i(&piece);
+        let mut undo = Undo {
+            captured: None,
+            castle_rights: self.castle_rights_bits(),
+            enp_target: self.enp_target,
+            fifty_move_count: self.fifty_move_count,
+            hash: self.hash,
+        };
+        // keys of the castling rights and enpassant file being replaced
+        let old_keys = zobrist::castle_rights_key(self) ^ zobrist::enp_key(self);
 
         let src_mask = utils::pos_mask(mov.src());
         let dest_mask = utils::pos_mask(mov.dest());
 
         match mov.move_meta() {
             MoveMeta::Castle{ is_short } => {
-                bitboards[match_piece_i(PieceType::King, player)] ^= utils::king_castle(player, is_short);
-                bitboards[match_piece_i(PieceType::Rook, player)] ^= utils::rook_castle(player, is_short);
+                let king_i = match_piece_i(PieceType::King, player);
+                let rook_i = match_piece_i(PieceType::Rook, player);
+                self.bitboards[king_i] ^= utils::king_castle(player, is_short);
+                self.bitboards[rook_i] ^= utils::rook_castle(player, is_short);
+                for pos in BitPositions(utils::king_castle(player, is_short)) {
+                    self.hash ^= zobrist::piece_key(king_i, pos);
+                }
+                for pos in BitPositions(utils::rook_castle(player, is_short)) {
+                    self.hash ^= zobrist::piece_key(rook_i, pos);
+                }
             },
             MoveMeta::Enpassant => {
-                bitboards[match_piece_i(PieceType::Pawn, !player)] ^= utils::pos_mask(
-                    if player { mov.dest()-8 } else { mov.dest()+8 }
-                );
-                bitboards[get_piece_i(&piece)] ^= src_mask | dest_mask;
-                if self.is_post_enp_checked(player) {
-                    return None
-                }
+                let captured_i = match_piece_i(PieceType::Pawn, !player);
+                let captured_mask = utils::pos_mask(self.enp_target);
+                self.bitboards[captured_i] ^= captured_mask;
+                self.bitboards[piece_i] ^= src_mask | dest_mask;
+                self.hash ^= zobrist::piece_key(captured_i, self.enp_target) ^
+                        zobrist::piece_key(piece_i, mov.src()) ^
+                        zobrist::piece_key(piece_i, mov.dest());
+                undo.captured = Some( get_piece(captured_i) );
             },
             MoveMeta::Promotion{ is_capture: _, piece_type } => {
-                // first clear all dest
-                for bb in &mut bitboards {
-                    *bb ^= dest_mask
-                }
-                bitboards[match_piece_i(piece_type, player)] ^= dest_mask;
-                bitboards[get_piece_i(&piece)] ^= src_mask;
+                undo.captured = self.capture_at(mov.dest());
+                let promo_i = match_piece_i(piece_type, player);
+                self.bitboards[promo_i] ^= dest_mask;
+                self.bitboards[piece_i] ^= src_mask;
+                self.hash ^= zobrist::piece_key(promo_i, mov.dest()) ^ zobrist::piece_key(piece_i, mov.src());
             },
             MoveMeta::Illegal => return None,
             _ => {
-                // first clear all dest
-                for bb in &mut bitboards {
-                    *bb &= !dest_mask
-                }
-                bitboards[get_piece_i(&piece)] ^= src_mask | dest_mask;
+                undo.captured = self.capture_at(mov.dest());
+                self.bitboards[piece_i] ^= src_mask | dest_mask;
+                self.hash ^= zobrist::piece_key(piece_i, mov.src()) ^ zobrist::piece_key(piece_i, mov.dest());
             }
-
         }
 
-        let mut next_board = self.from_self(bitboards);
+        self.player = !player;
+        self.half_move_count += 1;
+        self.enp_target = 0;
 
-        match mov.piece() {
+        match piece {
             Piece{ piece_type: PieceType::King, player } => {
-                next_board.unset_castle_rights(player, true);
-                next_board.unset_castle_rights(player, false);
+                self.unset_castle_rights(player, true);
+                self.unset_castle_rights(player, false);
             },
-            Piece{ piece_type: PieceType::Rook, player } => {
-                let (file, _) = utils::file_rank(mov.src());
-                let is_short = file == 0;
-                next_board.unset_castle_rights(player, is_short);
+            // if double pawn push, get enp target
+            Piece{ piece_type: PieceType::Pawn, player: _ } if utils::is_double_pawnpush(mov) => {
+                self.enp_target = mov.dest();
             },
-            Piece{ piece_type: PieceType::Pawn, player: _ } => {
-                // if double pawn push, get enp target
-                if utils::is_double_pawnpush(&mov) {
-                    next_board.enp_target = mov.dest();
+            _ => {}
+        }
+
+        // moving from or capturing on a rook's starting square loses the castling right on that side.
+        // castles have no real dest square
+        let touched = match mov.move_meta() {
+            MoveMeta::Castle{ .. } => 0,
+            _ => src_mask | dest_mask,
+        };
+        for &player in &[WHITE, BLACK] {
+            for &is_short in &[true, false] {
+                if utils::rook_home(player, is_short) & touched > 0 {
+                    self.unset_castle_rights(player, is_short);
                 }
             }
-            _ => {}
         }
 
+        // switch player, then swap the lost castling rights and the enpassant file
+        self.hash ^= zobrist::side_key() ^ old_keys ^ zobrist::castle_rights_key(self) ^ zobrist::enp_key(self);
+
         // 50 move rule: reset count if move is a pawn push or a capture
-        if mov.piece().piece_type == PieceType::Pawn || mov.move_meta().is_capture() {
-            next_board.fifty_move_count = 0;
+        if piece.piece_type == PieceType::Pawn || mov.move_meta().is_capture() {
+            self.fifty_move_count = 0;
         } else {
-            next_board.fifty_move_count += 1;
+            self.fifty_move_count = self.fifty_move_count.saturating_add(1);
         }
-        println!("{}", next_board);
-        Some( next_board )
+        Some( undo )
+    }
+    /// Take back {mov}, the last move played with `Board::make_move_inplace`
+    pub fn unmake_move(&mut self, mov: &Move, undo: &Undo) {
+        let piece = mov.piece();
+        let player = piece.player;
+        let piece_i = get_piece_i(&piece);
+        let src_mask = utils::pos_mask(mov.src());
+        let dest_mask = utils::pos_mask(mov.dest());
+
+        match mov.move_meta() {
+            MoveMeta::Castle{ is_short } => {
+                self.bitboards[match_piece_i(PieceType::King, player)] ^= utils::king_castle(player, is_short);
+                self.bitboards[match_piece_i(PieceType::Rook, player)] ^= utils::rook_castle(player, is_short);
+            },
+            MoveMeta::Enpassant => {
+                self.bitboards[piece_i] ^= src_mask | dest_mask;
+                self.bitboards[match_piece_i(PieceType::Pawn, !player)] ^= utils::pos_mask(undo.enp_target);
+            },
+            MoveMeta::Promotion{ is_capture: _, piece_type } => {
+                self.bitboards[match_piece_i(piece_type, player)] ^= dest_mask;
+                self.bitboards[piece_i] ^= src_mask;
+            },
+            _ => self.bitboards[piece_i] ^= src_mask | dest_mask,
+        }
+        if let (Some(captured), false) = (undo.captured, mov.move_meta() == MoveMeta::Enpassant) {
+            self.bitboards[get_piece_i(&captured)] ^= dest_mask;
+        }
+
+        self.player = player;
+        self.half_move_count -= 1;
+        self.set_castle_rights_bits(undo.castle_rights);
+        self.enp_target = undo.enp_target;
+        self.fifty_move_count = undo.fifty_move_count;
+        self.hash = undo.hash;
+    }
+    // take the opponent piece off {pos}, if there is one, and return it
+    fn capture_at(&mut self, pos: Square) -> Option<Piece> {
+        let pos_mask = utils::pos_mask(pos);
+        let opp_i = player_pieces_i(!self.player);
+        let captured_i = (opp_i..opp_i+6).find(|&i| self.bitboards[i] & pos_mask > 0)?;
+        self.bitboards[captured_i] ^= pos_mask;
+        self.hash ^= zobrist::piece_key(captured_i, pos);
+        Some( get_piece(captured_i) )
+    }
+    fn castle_rights_bits(&self) -> u8 {
+        (self.castle_w_s as u8) | (self.castle_w_l as u8) << 1 | (self.castle_b_s as u8) << 2 | (self.castle_b_l as u8) << 3
+    }
+    fn set_castle_rights_bits(&mut self, bits: u8) {
+        self.castle_w_s = bits & 1 > 0;
+        self.castle_w_l = bits & 2 > 0;
+        self.castle_b_s = bits & 4 > 0;
+        self.castle_b_l = bits & 8 > 0;
     }
 
     // Get a mapping/list of tuples with pinned_sq -> ray of pin. Also return the combined pinned pieces mask
@@ -414,6 +898,14 @@ impl<'a> Board {
     }
 }
 
+// the king is worth more than anything it could win, so it never captures into an attack
+fn see_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::King => 20_000,
+        _ => piece_type.cp_value(),
+    }
+}
+
 impl std::fmt::Display for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut board_chars = [['·'; 8]; 8];
@@ -1,3 +1,6 @@
+use crate::board::Square;
+use crate::utils;
+
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -23,3 +26,111 @@ impl std::error::Error for Error {
         &self.details
     }
 }
+
+/// Reasons an FEN string is rejected by `Board::from_fenstr`.
+/// Syntax errors come first, followed by positions that parse but can't occur in a game
+#[derive(Debug, PartialEq, Clone)]
+pub enum FenError {
+    /// Fewer than the 4 required sections (placement, player, castling, enpassant)
+    MissingSection,
+    /// More than the 6 sections in an FEN
+    ExtraSection,
+    /// Piece placement does not have 8 ranks
+    RankCount(usize),
+    /// A rank (1-8) does not describe exactly 8 squares
+    RankLength(u8),
+    InvalidPieceChar(char),
+    InvalidPlayer(String),
+    InvalidCastling(String),
+    InvalidEnpassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+
+    /// Each player needs exactly one king
+    KingCount { player: bool, count: u64 },
+    /// Pawns can't stand on the 1st or 8th rank
+    PawnOnBackRank(Square),
+    /// The player who just moved can't be left in check
+    OppInCheck,
+    /// Castling right without the king and rook on their starting squares
+    CastlingRights { player: bool, is_short: bool },
+    /// Enpassant target square that no double pawn push could have produced
+    BogusEnpassant(Square),
+}
+
+impl std::fmt::Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let player_str = |player: &bool| if *player { "white" } else { "black" };
+        match self {
+            FenError::MissingSection => write!(f, "FEN string has fewer than 4 sections"),
+            FenError::ExtraSection => write!(f, "Found more segments than expected"),
+            FenError::RankCount(n) => write!(f, "Piece placement string has {} ranks instead of 8", n),
+            FenError::RankLength(rank) => write!(f, "Rank {} does not describe 8 squares", rank),
+            FenError::InvalidPieceChar(c) => write!(f, "Piece placement string contains invalid character: {}", c),
+            FenError::InvalidPlayer(s) => write!(f, "Invalid next player: {}", s),
+            FenError::InvalidCastling(s) => write!(f, "Invalid castling rights: {}", s),
+            FenError::InvalidEnpassant(s) => write!(f, "Invalid enpassant target square: {}", s),
+            FenError::InvalidHalfmoveClock(s) => write!(f, "Invalid halfmove clock: {}", s),
+            FenError::InvalidFullmoveNumber(s) => write!(f, "Invalid fullmove number: {}", s),
+            FenError::KingCount{ player, count } => write!(f, "{} has {} kings", player_str(player), count),
+            FenError::PawnOnBackRank(pos) => write!(f, "Pawn on back rank at {}", utils::file_rank_str(*pos)),
+            FenError::OppInCheck => write!(f, "Player not to move is in check"),
+            FenError::CastlingRights{ player, is_short } => write!(
+                f, "{} {} castling right without king and rook on their starting squares",
+                player_str(player), if *is_short { "short" } else { "long" }
+            ),
+            FenError::BogusEnpassant(pos) => write!(f, "Bogus enpassant target square {}", utils::file_rank_str(*pos)),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+/// Reasons a move string is rejected by `Board::parse_uci_move` and `Board::parse_san`
+#[derive(Debug, PartialEq, Clone)]
+pub enum MoveParseError {
+    /// Not a move in the expected notation
+    Malformed(String),
+    /// Well formed, but no legal move matches
+    Illegal(String),
+    /// More than one legal move matches
+    Ambiguous(String),
+}
+
+impl std::fmt::Display for MoveParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MoveParseError::Malformed(s) => write!(f, "Malformed move: {}", s),
+            MoveParseError::Illegal(s) => write!(f, "Illegal move: {}", s),
+            MoveParseError::Ambiguous(s) => write!(f, "Ambiguous move: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for MoveParseError {}
+
+/// Reasons an evaluation parameter file is rejected by `EvalParams::from_str`
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParamsError {
+    UnknownField(String),
+    /// Not an integer
+    InvalidValue(String),
+    /// A field without exactly one mg and eg value per weight
+    ValueCount { field: &'static str, expected: usize, found: usize },
+    MissingField(&'static str),
+}
+
+impl std::fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParamsError::UnknownField(s) => write!(f, "Unknown eval parameter: {}", s),
+            ParamsError::InvalidValue(s) => write!(f, "Invalid eval parameter value: {}", s),
+            ParamsError::ValueCount{ field, expected, found } => write!(
+                f, "Eval parameter {} has {} values instead of {}", field, found, expected
+            ),
+            ParamsError::MissingField(s) => write!(f, "Missing eval parameter: {}", s),
+        }
+    }
+}
+
+impl std::error::Error for ParamsError {}
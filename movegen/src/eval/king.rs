@@ -0,0 +1,51 @@
+use crate::board::{Board, Direction};
+use crate::eval::{EvalParams, Score, type_i};
+use crate::eval::mobility::piece_attacks;
+use crate::eval::pawns::{fill, forward};
+use crate::moves::BitPositions;
+use crate::pieces::{PieceType, WHITE, BLACK};
+use crate::utils;
+
+/// Pawn shield, open files next to the king and enemy pieces bearing on the squares around it
+pub fn player_king_safety(params: &EvalParams, board: &Board, player: bool) -> Score {
+    let king = board.piece_bb(PieceType::King, player);
+    if king == 0 {
+        return Score::default()
+    }
+    let pawns = board.piece_bb(PieceType::Pawn, player);
+    let dir = forward(player);
+    let mut score = Score::default();
+
+    // the king's and the adjacent files
+    let near = king | utils::slide(king, 1, &Direction::E) | utils::slide(king, 1, &Direction::W);
+    let shield = utils::slide(near, 1, &dir);
+    let shield = shield | utils::slide(shield, 1, &dir);
+    score += params.pawn_shield * utils::n_set_bits(pawns & shield) as i32;
+    for pos in BitPositions(near) {
+        let file = fill(utils::pos_mask(pos), &Direction::N) | fill(utils::pos_mask(pos), &Direction::S);
+        if file & pawns == 0 {
+            score += params.king_open_file;
+        }
+    }
+
+    let king_pos = king.trailing_zeros() as u8;
+    let zone = utils::king_attack(king_pos) | king;
+    let mut attackers = 0;
+    let mut attack = Score::default();
+    for (piece_type, attack_mask) in piece_attacks(board, !player) {
+        if attack_mask & zone > 0 {
+            attackers += 1;
+            attack += params.king_attack[type_i(piece_type)];
+        }
+    }
+    // a lone attacker rarely gets anywhere
+    if attackers > 1 {
+        score += attack;
+    }
+    score
+}
+
+/// King safety of white less black's
+pub fn king_safety(params: &EvalParams, board: &Board) -> Score {
+    player_king_safety(params, board, WHITE) - player_king_safety(params, board, BLACK)
+}
@@ -0,0 +1,38 @@
+use crate::board::{Board, BitBoard};
+use crate::eval::{EvalParams, Score, type_i};
+use crate::eval::pawns::pawn_attacks;
+use crate::moves::BitPositions;
+use crate::pieces::{Piece, PieceType, WHITE, BLACK};
+use crate::utils;
+
+/// The piece types with mobility, in `EvalParams` order
+pub const MOBILE_PIECES: [PieceType; 4] = [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen];
+
+/// Attack mask of each of {player}'s knights, bishops, rooks and queens
+pub fn piece_attacks(board: &Board, player: bool) -> Vec<(PieceType, BitBoard)> {
+    let empty = board.empty_mask();
+    let mut attacks = Vec::with_capacity(16);
+    for &piece_type in MOBILE_PIECES.iter() {
+        let piece = Piece{ piece_type, player };
+        for pos in BitPositions(board.piece_bb(piece_type, player)) {
+            let (attack_mask, _) = piece.attack_check_mask(&utils::pos_mask(pos), &empty, &0);
+            attacks.push((piece_type, attack_mask));
+        }
+    }
+    attacks
+}
+
+/// Squares each piece can go to that aren't held by its own pieces or attacked by enemy pawns
+pub fn player_mobility(params: &EvalParams, board: &Board, player: bool) -> Score {
+    let safe = !board.player_mask(player) & !pawn_attacks(board.piece_bb(PieceType::Pawn, !player), !player);
+    let mut score = Score::default();
+    for (piece_type, attack_mask) in piece_attacks(board, player) {
+        score += params.mobility[type_i(piece_type)] * utils::n_set_bits(attack_mask & safe) as i32;
+    }
+    score
+}
+
+/// Mobility of white's pieces less black's
+pub fn mobility(params: &EvalParams, board: &Board) -> Score {
+    player_mobility(params, board, WHITE) - player_mobility(params, board, BLACK)
+}
@@ -0,0 +1,198 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+use crate::board::Board;
+use crate::moves::BitPositions;
+use crate::pieces::{PieceType, WHITE, BLACK};
+use crate::tt::Table;
+use crate::utils;
+use crate::zobrist;
+
+mod params;
+mod trace;
+pub mod pawns;
+pub mod mobility;
+pub mod king;
+
+pub use params::EvalParams;
+pub use trace::{EvalTrace, TermTrace};
+use pawns::PawnEntry;
+
+/// Default size of the pawn structure cache
+pub const PAWN_TABLE_MB: usize = 1;
+
+/// Phase weight of each piece type, indexed pawn, knight, bishop, rook, queen, king
+pub const PHASE_WEIGHTS: [i32; 6] = [1, 2, 2, 4, 6, 0];
+/// Phase of the start position. More material than this counts as the start position
+pub const MAX_PHASE: i32 = 16 + 4*2 + 4*2 + 4*4 + 2*6;
+
+/// Index of {piece_type} in the `EvalParams` piece arrays
+pub fn type_i(piece_type: PieceType) -> usize {
+    piece_type as usize - PieceType::Pawn as usize
+}
+
+/// A midgame and an endgame value, blended by the game phase
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub fn new(mg: i32, eg: i32) -> Self {
+        Score{ mg, eg }
+    }
+    /// Blend the midgame and endgame values. {phase} goes from MAX_PHASE at the start to 0 with bare kings
+    pub fn taper(&self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+    fn add(self, other: Score) -> Score {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+    fn sub(self, other: Score) -> Score {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+    fn neg(self) -> Score {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+    fn mul(self, n: i32) -> Score {
+        Score::new(self.mg * n, self.eg * n)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Score) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, other: Score) {
+        *self = *self - other;
+    }
+}
+
+/// Game phase from the material on the board, MAX_PHASE at the start down to 0 with bare kings
+pub fn game_phase(board: &Board) -> i32 {
+    let phase: i32 = [WHITE, BLACK].iter()
+            .flat_map(|&player| board.player_bbs(player).iter().zip(PHASE_WEIGHTS.iter()))
+            .map(|(bb, weight)| utils::n_set_bits(*bb) as i32 * weight)
+            .sum();
+    phase.min(MAX_PHASE)
+}
+
+/// Handcrafted evaluation. Terms are scored from white's view, `evaluate` turns them to the side to move's
+pub struct Evaluator {
+    params: EvalParams,
+    // pawn structures by `zobrist::pawn_hash`
+    pawn_table: Table<PawnEntry>,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Evaluator::new(EvalParams::default())
+    }
+}
+
+impl Evaluator {
+    pub fn new(params: EvalParams) -> Self {
+        Evaluator {
+            params,
+            pawn_table: Table::new(PAWN_TABLE_MB),
+        }
+    }
+
+    pub fn params(&self) -> &EvalParams {
+        &self.params
+    }
+
+    /// Replace the weights. Cached pawn structures scored with the old ones are dropped
+    pub fn set_params(&mut self, params: EvalParams) {
+        self.params = params;
+        self.pawn_table.clear();
+    }
+
+    /// Centipawns from the side to move's view
+    pub fn evaluate(&mut self, board: &Board) -> i32 {
+        let score = self.material(board) + self.psqt(board) + self.pawns(board) +
+                self.mobility(board) + self.king_safety(board) + self.tempo(board);
+        let cp = score.taper(game_phase(board));
+        if board.player { cp } else { -cp }
+    }
+
+    /// Material balance
+    pub fn material(&self, board: &Board) -> Score {
+        self.player_material(board, WHITE) - self.player_material(board, BLACK)
+    }
+
+    fn player_material(&self, board: &Board, player: bool) -> Score {
+        let mut score = Score::default();
+        for (weight, bb) in self.params.material.iter().zip(board.player_bbs(player)) {
+            score += *weight * utils::n_set_bits(*bb) as i32;
+        }
+        score
+    }
+
+    /// Piece-square table bonuses
+    pub fn psqt(&self, board: &Board) -> Score {
+        self.player_psqt(board, WHITE) - self.player_psqt(board, BLACK)
+    }
+
+    fn player_psqt(&self, board: &Board, player: bool) -> Score {
+        // black's squares are mirrored onto white's
+        let flip = if player { 0 } else { 56 };
+        let mut score = Score::default();
+        for (table, bb) in self.params.pst.iter().zip(board.player_bbs(player)) {
+            for pos in BitPositions(*bb) {
+                score += table[pos as usize ^ flip];
+            }
+        }
+        score
+    }
+
+    /// Bonus for having the move, from white's view
+    pub fn tempo(&self, board: &Board) -> Score {
+        if board.player { self.params.tempo } else { -self.params.tempo }
+    }
+
+    /// Doubled, isolated, backward, connected and passed pawns
+    pub fn pawns(&mut self, board: &Board) -> Score {
+        let key = zobrist::pawn_hash(board);
+        let entry = match self.pawn_table.probe(key) {
+            Some(entry) => entry,
+            None => {
+                let entry = pawns::pawn_structure(&self.params, board);
+                self.pawn_table.store(key, entry);
+                entry
+            },
+        };
+        entry.score +
+            pawns::free_passers(&self.params, board, entry.passed, WHITE) -
+            pawns::free_passers(&self.params, board, entry.passed, BLACK)
+    }
+
+    /// Squares the pieces can move to, leaving out those enemy pawns attack
+    pub fn mobility(&self, board: &Board) -> Score {
+        mobility::mobility(&self.params, board)
+    }
+
+    /// Pawn shield, open files and attacks around the kings
+    pub fn king_safety(&self, board: &Board) -> Score {
+        king::king_safety(&self.params, board)
+    }
+}
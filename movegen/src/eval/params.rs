@@ -0,0 +1,267 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::ParamsError;
+use crate::eval::Score;
+
+// names of the piece-square tables in a params file
+const PST_NAMES: [&str; 6] = ["pst_pawn", "pst_knight", "pst_bishop", "pst_rook", "pst_queen", "pst_king"];
+
+const fn s(mg: i32, eg: i32) -> Score {
+    Score{ mg, eg }
+}
+
+/// Weights of the evaluation terms. Piece arrays are indexed pawn, knight, bishop, rook, queen, king
+#[derive(Clone, PartialEq, Debug)]
+pub struct EvalParams {
+    pub material: [Score; 6],
+    /// Bonus of a piece on a square, from white's view with a1 = 0. Black's are mirrored
+    pub pst: [[Score; 64]; 6],
+    /// Per pawn behind another one on the same file
+    pub doubled: Score,
+    /// Per pawn without pawns on the adjacent files
+    pub isolated: Score,
+    /// Per pawn whose stop square is held by an enemy pawn and can't be defended by a pawn
+    pub backward: Score,
+    /// Per pawn defended by or next to another pawn
+    pub connected: Score,
+    /// Per passed pawn, by rank counted from its own side
+    pub passed: [Score; 8],
+    /// Extra for a passed pawn with nothing in front of it, by rank
+    pub passed_free: [Score; 8],
+    /// Per square a piece can safely move to
+    pub mobility: [Score; 6],
+    /// Per pawn in the two ranks in front of the king, on its own or the adjacent files
+    pub pawn_shield: Score,
+    /// Per file next to or under the king without own pawns
+    pub king_open_file: Score,
+    /// Per enemy piece attacking the squares around the king, counted when there are at least two
+    pub king_attack: [Score; 6],
+    /// Bonus of the side to move
+    pub tempo: Score,
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams {
+            material: [s(100, 120), s(320, 300), s(330, 320), s(500, 530), s(900, 950), s(0, 0)],
+            pst: [
+                pst(&PAWN_PST, &PAWN_PST),
+                pst(&KNIGHT_PST, &KNIGHT_PST),
+                pst(&BISHOP_PST, &BISHOP_PST),
+                pst(&ROOK_PST, &ROOK_PST),
+                pst(&QUEEN_PST, &QUEEN_PST),
+                pst(&KING_MG_PST, &KING_EG_PST),
+            ],
+            doubled: s(-10, -20),
+            isolated: s(-10, -15),
+            backward: s(-8, -10),
+            connected: s(8, 6),
+            passed: [s(0, 0), s(5, 10), s(5, 15), s(10, 25), s(20, 45), s(35, 75), s(60, 120), s(0, 0)],
+            passed_free: [s(0, 0), s(0, 0), s(0, 5), s(0, 10), s(5, 20), s(10, 35), s(20, 60), s(0, 0)],
+            mobility: [s(0, 0), s(4, 4), s(3, 5), s(2, 4), s(1, 2), s(0, 0)],
+            pawn_shield: s(12, 0),
+            king_open_file: s(-20, 0),
+            king_attack: [s(0, 0), s(-15, -5), s(-15, -5), s(-20, -5), s(-35, -10), s(0, 0)],
+            tempo: s(10, 5),
+        }
+    }
+}
+
+impl EvalParams {
+    /// Every weight by field name, in the order they are written out. Tables are a1 = 0
+    pub fn fields(&self) -> Vec<(&'static str, &[Score])> {
+        let mut fields: Vec<(&'static str, &[Score])> = vec![("material", &self.material)];
+        fields.extend(PST_NAMES.iter().zip(self.pst.iter()).map(|(name, table)| (*name, &table[..])));
+        fields.extend(vec![
+            ("doubled", std::slice::from_ref(&self.doubled)),
+            ("isolated", std::slice::from_ref(&self.isolated)),
+            ("backward", std::slice::from_ref(&self.backward)),
+            ("connected", std::slice::from_ref(&self.connected)),
+            ("passed", &self.passed[..]),
+            ("passed_free", &self.passed_free[..]),
+            ("mobility", &self.mobility[..]),
+            ("pawn_shield", std::slice::from_ref(&self.pawn_shield)),
+            ("king_open_file", std::slice::from_ref(&self.king_open_file)),
+            ("king_attack", &self.king_attack[..]),
+            ("tempo", std::slice::from_ref(&self.tempo)),
+        ]);
+        fields
+    }
+
+    pub fn fields_mut(&mut self) -> Vec<(&'static str, &mut [Score])> {
+        let mut fields: Vec<(&'static str, &mut [Score])> = vec![("material", &mut self.material)];
+        fields.extend(PST_NAMES.iter().zip(self.pst.iter_mut()).map(|(name, table)| (*name, &mut table[..])));
+        fields.extend(vec![
+            ("doubled", std::slice::from_mut(&mut self.doubled)),
+            ("isolated", std::slice::from_mut(&mut self.isolated)),
+            ("backward", std::slice::from_mut(&mut self.backward)),
+            ("connected", std::slice::from_mut(&mut self.connected)),
+            ("passed", &mut self.passed[..]),
+            ("passed_free", &mut self.passed_free[..]),
+            ("mobility", &mut self.mobility[..]),
+            ("pawn_shield", std::slice::from_mut(&mut self.pawn_shield)),
+            ("king_open_file", std::slice::from_mut(&mut self.king_open_file)),
+            ("king_attack", &mut self.king_attack[..]),
+            ("tempo", std::slice::from_mut(&mut self.tempo)),
+        ]);
+        fields
+    }
+
+    /// Every weight in `fields` order
+    pub fn to_vec(&self) -> Vec<Score> {
+        self.fields().into_iter().flat_map(|(_, weights)| weights.iter().copied()).collect()
+    }
+
+    /// Set the weights from {weights} in `fields` order. Missing ones are left as they are
+    pub fn set_from(&mut self, weights: &[Score]) {
+        let mut weights = weights.iter();
+        for (_, field) in self.fields_mut() {
+            for (weight, value) in field.iter_mut().zip(&mut weights) {
+                *weight = *value;
+            }
+        }
+    }
+
+    /// Read the weights from a file written by `Display`
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok( std::fs::read_to_string(path)?.parse()? )
+    }
+}
+
+/// One line per field, its name followed by the mg and eg value of each weight
+impl fmt::Display for EvalParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, weights) in self.fields() {
+            write!(f, "{}", name)?;
+            for weight in weights {
+                write!(f, " {} {}", weight.mg, weight.eg)?;
+            }
+            writeln!(f)?;
+        }
+        Ok( () )
+    }
+}
+
+/// Parse the `Display` format. Every field is needed, blank lines and lines starting with # are skipped
+impl FromStr for EvalParams {
+    type Err = ParamsError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut params = EvalParams::default();
+        let mut seen = Vec::new();
+        {
+            let mut fields = params.fields_mut();
+            for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+                let mut tokens = line.split_whitespace();
+                let name = tokens.next().unwrap_or_default();
+                let field = fields.iter_mut()
+                        .find(|(field_name, _)| *field_name == name)
+                        .ok_or_else(|| ParamsError::UnknownField(name.to_string()))?;
+                let values = tokens
+                        .map(|v| v.parse::<i32>().map_err(|_| ParamsError::InvalidValue(v.to_string())))
+                        .collect::<Result<Vec<i32>, ParamsError>>()?;
+                if values.len() != field.1.len() * 2 {
+                    return Err( ParamsError::ValueCount{ field: field.0, expected: field.1.len() * 2, found: values.len() } )
+                }
+                for (weight, value) in field.1.iter_mut().zip(values.chunks(2)) {
+                    *weight = s(value[0], value[1]);
+                }
+                seen.push(field.0);
+            }
+        }
+        match params.fields().iter().find(|(name, _)| !seen.contains(name)) {
+            Some((name, _)) => Err( ParamsError::MissingField(name) ),
+            None => Ok( params ),
+        }
+    }
+}
+
+// Tables below are laid out as seen from white's side, rank 8 first
+fn pst(mg: &[i32; 64], eg: &[i32; 64]) -> [Score; 64] {
+    let mut table = [Score::default(); 64];
+    for (pos, score) in table.iter_mut().enumerate() {
+        // flip the rank to get a1 = 0
+        *score = s(mg[pos ^ 56], eg[pos ^ 56]);
+    }
+    table
+}
+
+const PAWN_PST: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+const KNIGHT_PST: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+const BISHOP_PST: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+const ROOK_PST: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+const QUEEN_PST: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+// stay behind the pawn shield in the middlegame
+const KING_MG_PST: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+// and head for the centre in the endgame
+const KING_EG_PST: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
@@ -0,0 +1,131 @@
+use crate::board::{Board, BitBoard, Direction, file_bb};
+use crate::eval::{EvalParams, Score};
+use crate::moves::BitPositions;
+use crate::pieces::{PieceType, WHITE, BLACK};
+use crate::tt::TableEntry;
+use crate::utils;
+
+const FILES: [BitBoard; 8] = [
+    file_bb::A, file_bb::B, file_bb::C, file_bb::D, file_bb::E, file_bb::F, file_bb::G, file_bb::H,
+];
+
+/// Pawn structure of a position, cached in the evaluator's pawn table
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct PawnEntry {
+    /// Doubled, isolated, backward, connected and passed pawn terms from white's view
+    pub score: Score,
+    /// Passed pawns of both players. Their free path bonus depends on the pieces, so it isn't cached
+    pub passed: BitBoard,
+}
+
+impl TableEntry for PawnEntry {
+    // pawn structures are cheap to recompute, always replace
+    fn priority(&self, _age: u8) -> i32 {
+        0
+    }
+}
+
+/// The direction {player}'s pawns move in
+pub fn forward(player: bool) -> Direction {
+    if player { Direction::N } else { Direction::S }
+}
+
+/// {bb} and every square {dir} of it
+pub fn fill(bb: BitBoard, dir: &Direction) -> BitBoard {
+    let mut bb = bb;
+    bb |= utils::slide(bb, 1, dir);
+    bb |= utils::slide(bb, 2, dir);
+    bb |= utils::slide(bb, 4, dir);
+    bb
+}
+
+/// The squares in front of {pawns} on their files
+pub fn front_span(pawns: BitBoard, player: bool) -> BitBoard {
+    let dir = forward(player);
+    fill(utils::slide(pawns, 1, &dir), &dir)
+}
+
+/// Squares attacked by {player}'s {pawns}
+pub fn pawn_attacks(pawns: BitBoard, player: bool) -> BitBoard {
+    if player {
+        utils::slide(pawns, 1, &Direction::NE) | utils::slide(pawns, 1, &Direction::NW)
+    } else {
+        utils::slide(pawns, 1, &Direction::SE) | utils::slide(pawns, 1, &Direction::SW)
+    }
+}
+
+fn adjacent_files(bb: BitBoard) -> BitBoard {
+    let files = fill(bb, &Direction::N) | fill(bb, &Direction::S);
+    utils::slide(files, 1, &Direction::E) | utils::slide(files, 1, &Direction::W)
+}
+
+/// {player}'s pawns without opposing pawns in front of them on their own or the adjacent files
+pub fn passed_pawns(board: &Board, player: bool) -> BitBoard {
+    let pawns = board.piece_bb(PieceType::Pawn, player);
+    let opp_span = front_span(board.piece_bb(PieceType::Pawn, !player), !player);
+    let blocked = opp_span | utils::slide(opp_span, 1, &Direction::E) | utils::slide(opp_span, 1, &Direction::W);
+    pawns & !blocked
+}
+
+/// Rank of {pos} counted from {player}'s side, 0-7
+pub fn relative_rank(pos: u8, player: bool) -> usize {
+    let (_, rank) = utils::file_rank(pos);
+    if player { rank as usize } else { 7 - rank as usize }
+}
+
+/// Pawn structure terms of {player}, and the passed pawns
+pub fn player_structure(params: &EvalParams, board: &Board, player: bool) -> (Score, BitBoard) {
+    let pawns = board.piece_bb(PieceType::Pawn, player);
+    let opp_pawns = board.piece_bb(PieceType::Pawn, !player);
+    let dir = forward(player);
+    let back = forward(!player);
+    let mut score = Score::default();
+
+    // every pawn past the first on a file
+    for file in FILES.iter() {
+        let n = utils::n_set_bits(pawns & file) as i32;
+        if n > 1 {
+            score += params.doubled * (n - 1);
+        }
+    }
+
+    let isolated = pawns & !adjacent_files(pawns);
+    score += params.isolated * utils::n_set_bits(isolated) as i32;
+
+    // the stop square is held by an enemy pawn and no pawn on an adjacent file can come up to defend it
+    let attacks = pawn_attacks(pawns, player);
+    let support_span = fill(attacks, &dir);
+    let stops = utils::slide(pawns, 1, &dir) & pawn_attacks(opp_pawns, !player) & !support_span;
+    let backward = utils::slide(stops, 1, &back) & !isolated;
+    score += params.backward * utils::n_set_bits(backward) as i32;
+
+    // defended by a pawn or side by side with one
+    let phalanx = pawns & (utils::slide(pawns, 1, &Direction::E) | utils::slide(pawns, 1, &Direction::W));
+    let connected = pawns & (attacks | phalanx);
+    score += params.connected * utils::n_set_bits(connected) as i32;
+
+    let passed = passed_pawns(board, player);
+    for pos in BitPositions(passed) {
+        score += params.passed[relative_rank(pos, player)];
+    }
+    (score, passed)
+}
+
+/// Pawn structure of both players, the part of the pawn evaluation that only depends on the pawns
+pub fn pawn_structure(params: &EvalParams, board: &Board) -> PawnEntry {
+    let (white, white_passed) = player_structure(params, board, WHITE);
+    let (black, black_passed) = player_structure(params, board, BLACK);
+    PawnEntry{ score: white - black, passed: white_passed | black_passed }
+}
+
+/// Bonus of {player}'s passed pawns in {passed} with nothing standing in front of them
+pub fn free_passers(params: &EvalParams, board: &Board, passed: BitBoard, player: bool) -> Score {
+    let occupied = !board.empty_mask();
+    let mut score = Score::default();
+    for pos in BitPositions(passed & board.piece_bb(PieceType::Pawn, player)) {
+        if front_span(utils::pos_mask(pos), player) & occupied == 0 {
+            score += params.passed_free[relative_rank(pos, player)];
+        }
+    }
+    score
+}
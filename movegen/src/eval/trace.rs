@@ -0,0 +1,103 @@
+use std::fmt;
+
+use crate::board::Board;
+use crate::eval::{Evaluator, Score, MAX_PHASE, game_phase, pawns, mobility, king};
+use crate::pieces::{WHITE, BLACK};
+
+/// One evaluation term for each side
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct TermTrace {
+    pub name: &'static str,
+    pub white: Score,
+    pub black: Score,
+}
+
+impl TermTrace {
+    /// White's score less black's
+    pub fn total(&self) -> Score {
+        self.white - self.black
+    }
+}
+
+/// Every term of an evaluation, from `Evaluator::trace`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EvalTrace {
+    /// material, pst, pawns, mobility, king safety and tempo, in that order
+    pub terms: Vec<TermTrace>,
+    pub phase: i32,
+    pub player: bool,
+    /// The tapered sum of the terms, from the side to move's view like `Evaluator::evaluate`
+    pub eval: i32,
+}
+
+impl EvalTrace {
+    /// Sum of the terms, from white's view
+    pub fn total(&self) -> Score {
+        self.terms.iter().fold(Score::default(), |acc, term| acc + term.total())
+    }
+
+    pub fn term(&self, name: &str) -> Option<&TermTrace> {
+        self.terms.iter().find(|term| term.name == name)
+    }
+
+    /// The trace as a JSON object:
+    /// {"phase": p, "max_phase": m, "player": "white"|"black", "eval": cp,
+    ///  "terms": {"<name>": {"white": {"mg": x, "eg": y}, "black": {..}, "total": {..}}, ..}}
+    pub fn to_json(&self) -> String {
+        let score_json = |score: Score| format!("{{\"mg\": {}, \"eg\": {}}}", score.mg, score.eg);
+        let terms: Vec<String> = self.terms.iter()
+                .map(|term| format!(
+                    "\"{}\": {{\"white\": {}, \"black\": {}, \"total\": {}}}",
+                    term.name, score_json(term.white), score_json(term.black), score_json(term.total())
+                ))
+                .collect();
+        format!(
+            "{{\"phase\": {}, \"max_phase\": {}, \"player\": \"{}\", \"eval\": {}, \"terms\": {{{}}}}}",
+            self.phase, MAX_PHASE, if self.player { "white" } else { "black" }, self.eval, terms.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:12} | {:>5} {:>5} | {:>5} {:>5} | {:>5} {:>5}", "term", "white", "", "black", "", "total", "")?;
+        writeln!(f, "{:12} | {:>5} {:>5} | {:>5} {:>5} | {:>5} {:>5}", "", "mg", "eg", "mg", "eg", "mg", "eg")?;
+        writeln!(f, "{:-<12}-+-{:-<11}-+-{:-<11}-+-{:-<11}", "", "", "", "")?;
+        for term in self.terms.iter() {
+            let total = term.total();
+            writeln!(f, "{:12} | {:>5} {:>5} | {:>5} {:>5} | {:>5} {:>5}",
+                term.name, term.white.mg, term.white.eg, term.black.mg, term.black.eg, total.mg, total.eg)?;
+        }
+        writeln!(f, "{:-<12}-+-{:-<11}-+-{:-<11}-+-{:-<11}", "", "", "", "")?;
+        let total = self.total();
+        writeln!(f, "{:12} | {:>11} | {:>11} | {:>5} {:>5}", "total", "", "", total.mg, total.eg)?;
+        write!(f, "phase {}/{}, eval {} cp for {}", self.phase, MAX_PHASE, self.eval, if self.player { "white" } else { "black" })
+    }
+}
+
+impl Evaluator {
+    /// Each term of the evaluation of {board} by side and phase.
+    /// Pawn structures are recomputed rather than read from the pawn table
+    pub fn trace(&self, board: &Board) -> EvalTrace {
+        let params = &self.params;
+        let side = |f: &dyn Fn(bool) -> Score| (f(WHITE), f(BLACK));
+        let mut terms = Vec::new();
+        let mut push = |name, (white, black)| terms.push(TermTrace{ name, white, black });
+
+        push("material", side(&|player| self.player_material(board, player)));
+        push("pst", side(&|player| self.player_psqt(board, player)));
+        push("pawns", side(&|player| {
+            let (score, passed) = pawns::player_structure(params, board, player);
+            score + pawns::free_passers(params, board, passed, player)
+        }));
+        push("mobility", side(&|player| mobility::player_mobility(params, board, player)));
+        push("king safety", side(&|player| king::player_king_safety(params, board, player)));
+        push("tempo", side(&|player| if player == board.player { params.tempo } else { Score::default() }));
+
+        let phase = game_phase(board);
+        let mut trace = EvalTrace{ terms, phase, player: board.player, eval: 0 };
+        let cp = trace.total().taper(phase);
+        trace.eval = if board.player { cp } else { -cp };
+        trace
+    }
+}
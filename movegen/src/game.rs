@@ -0,0 +1,111 @@
+use crate::board::{Board, BoardStatus};
+use crate::moves::Move;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum DrawReason {
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+}
+
+/// How a finished game ended
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Outcome {
+    Checkmate { winner: bool },
+    Draw(DrawReason),
+}
+
+impl Outcome {
+    /// The winning player, None for a draw
+    pub fn winner(&self) -> Option<bool> {
+        match self {
+            Outcome::Checkmate{ winner } => Some(*winner),
+            Outcome::Draw(_) => None,
+        }
+    }
+}
+
+/// A game played from a start position, with the moves and position hashes
+/// needed for draw detection
+pub struct Game {
+    start_board: Board,
+    board: Board,
+    moves: Vec<Move>,
+    // hash of every position reached, the start and current positions included
+    hashes: Vec<u64>,
+}
+
+impl Game {
+    pub fn new(start_board: Board) -> Game {
+        Game {
+            start_board,
+            board: start_board,
+            moves: Vec::new(),
+            hashes: vec![start_board.hash],
+        }
+    }
+    pub fn standard() -> Game {
+        Game::new(Board::standard())
+    }
+    pub fn start_board(&self) -> &Board {
+        &self.start_board
+    }
+    /// The current position
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+    /// Hashes of the positions before the current one
+    pub fn history(&self) -> &[u64] {
+        &self.hashes[..self.hashes.len() - 1]
+    }
+    /// Play {mov}, expected to come from the current board's `move_list`.
+    /// Returns the new position, or None for `Move::invalid()`
+    pub fn make_move(&mut self, mov: &Move) -> Option<&Board> {
+        trace!("make move {} on {}", mov, self.board.to_fen());
+        let next_board = self.board.make_move(mov)?;
+        self.board = next_board;
+        self.moves.push(*mov);
+        self.hashes.push(next_board.hash);
+        Some( &self.board )
+    }
+    /// The current position has occured at least 3 times.
+    /// Only positions since the last capture or pawn move can repeat, with the same player to move
+    pub fn is_threefold(&self) -> bool {
+        let current = self.board.hash;
+        self.hashes.iter()
+                .rev()
+                .take(self.board.fifty_move_count as usize + 1)
+                .step_by(2)
+                .filter(|&&hash| hash == current)
+                .count() >= 3
+    }
+    /// 50 moves by each player without a capture or pawn move
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.board.fifty_move_count >= 100
+    }
+    pub fn is_insufficient_material(&self) -> bool {
+        self.board.is_insufficient_material()
+    }
+    /// The outcome of the game if the current position is terminal.
+    /// Checkmate takes precedence over the fifty move rule
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self.board.status() {
+            BoardStatus::Checkmate => return Some( Outcome::Checkmate{ winner: !self.board.player } ),
+            BoardStatus::Stalemate => return Some( Outcome::Draw(DrawReason::Stalemate) ),
+            BoardStatus::Ongoing => {},
+        }
+        if self.is_insufficient_material() {
+            Some( Outcome::Draw(DrawReason::InsufficientMaterial) )
+        } else if self.is_threefold() {
+            Some( Outcome::Draw(DrawReason::ThreefoldRepetition) )
+        } else if self.is_fifty_move_draw() {
+            Some( Outcome::Draw(DrawReason::FiftyMoveRule) )
+        } else {
+            None
+        }
+    }
+}
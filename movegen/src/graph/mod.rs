@@ -82,6 +82,7 @@ pub fn boards_2_graphs<'a>(
     boards: impl Iterator::<Item=&'a Board>
 ) -> Graphs {
     // let n = &boards.count();
+    debug!("building {} graphs", n);
     let mut moves           = ndarray::Array3::default( (n, 64, 64) );
     let mut occup_piecetype = ndarray::Array2::default( (n, 64,) );
     let mut occup_player    = ndarray::Array2::default( (n, 64,) );
@@ -120,6 +121,7 @@ pub fn save_graphs(
     } else {
         [filename, ".h5"].concat() 
     };
+    info!("saving {} graphs to {}", n, filename);
     let file = hdf5::File::create(filename)?;
     let piece_types = file.new_dataset::<PieceType>().create("piece_types", 7)?;
     piece_types.write(&[
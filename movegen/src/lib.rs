@@ -3,17 +3,40 @@ pub mod moves;
 pub mod pieces;
 pub mod utils;
 pub mod perft;
+pub mod zobrist;
+pub mod game;
+pub mod search;
+pub mod eval;
+pub mod tt;
 pub mod graph;
 pub mod uci;
 pub mod error;
 pub mod macros;
 
 #[macro_use] extern crate lazy_static;
+#[macro_use] extern crate log;
 
 #[cfg(test)]
 mod tests {
     use crate::perft;
+    use crate::tt;
+    use crate::utils;
     use crate::uci;
+    use crate::board::{Board, BoardStatus};
+    use crate::moves::{MoveList, Move, MoveMeta};
+    use crate::game::{Game, Outcome, DrawReason};
+    use crate::error::{FenError, MoveParseError, ParamsError};
+    use crate::pieces::{PieceType, WHITE, BLACK};
+    use crate::search::{Searcher, SearchLimits, SearchResult};
+    use crate::tt::{Table, TableEntry, TransTable, TTEntry, PerftEntry, Bound};
+    use crate::eval::{self, pawns, Evaluator, EvalParams, Score};
+    use crate::zobrist;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::time::{Duration, Instant};
+
+    const STARTPOS_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const KIWIPETE_FEN: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
 
     #[test]
     fn perft_4() {
@@ -24,6 +47,790 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fen_startpos() {
+        let board = Board::from_fenstr(STARTPOS_FEN).unwrap();
+        assert_eq!(board, Board::standard());
+        assert_eq!(Board::standard().to_fen(), STARTPOS_FEN);
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for &(fen, depth) in &[(STARTPOS_FEN, 3), (KIWIPETE_FEN, 2)] {
+            let start_board = Board::from_fenstr(fen).unwrap();
+            assert_eq!(start_board.to_fen(), fen);
+            perft::run_for_each(start_board, depth, |board| {
+                let fen = board.to_fen();
+                assert_eq!(Board::from_fenstr(&fen).as_ref(), Ok(board), "{}", fen);
+            });
+        }
+    }
+
+    #[test]
+    fn fen_clocks() {
+        let board = Board::from_fenstr("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 7 12").unwrap();
+        assert_eq!(board.fifty_move_count, 7);
+        assert_eq!(board.half_move_count, 23);
+        let board = Board::from_fenstr("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -").unwrap();
+        assert_eq!(board, Board::standard());
+    }
+
+    #[test]
+    fn fen_validation() {
+        let invalid = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq", FenError::MissingSection),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 x", FenError::ExtraSection),
+            ("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::RankCount(7)),
+            ("rnbqkbnr/pppppppp/8/8/8/9/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPieceChar('9')),
+            ("rnbqkbnr/pppppppp/8/8/4P4/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1", FenError::RankLength(4)),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1", FenError::InvalidPlayer("x".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkqK - 0 1", FenError::InvalidCastling("KQkqK".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1", FenError::InvalidEnpassant("e9".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1", FenError::InvalidHalfmoveClock("-1".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0", FenError::InvalidFullmoveNumber("0".to_string())),
+            // the plies played would overflow
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 40000", FenError::InvalidFullmoveNumber("40000".to_string())),
+            ("rnbqqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1", FenError::KingCount{ player: BLACK, count: 0 }),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBKKBNR w kq - 0 1", FenError::KingCount{ player: WHITE, count: 2 }),
+            ("rnbqkbnr/pppppppp/8/8/8/8/1PPPPPPP/PNBQKBNR w kq - 0 1", FenError::PawnOnBackRank(0)),
+            ("rnb1kbnr/pppppppp/8/8/1q6/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1", FenError::OppInCheck),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w KQkq - 0 1", FenError::CastlingRights{ player: WHITE, is_short: true }),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq e3 0 1", FenError::BogusEnpassant(20)),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e3 0 1", FenError::BogusEnpassant(20)),
+        ];
+        for (fen, err) in invalid.iter() {
+            assert_eq!(Board::from_fenstr(fen).as_ref(), Err(err), "{}", fen);
+        }
+
+        // black's move would take the ply count past u16::MAX
+        assert_eq!(Board::from_fenstr("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 32768"),
+                Err(FenError::InvalidFullmoveNumber("32768".to_string())));
+        // the last fullmove the board can play from, and a halfmove clock at its limit
+        let mut board = Board::from_fenstr("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 255 32768").unwrap();
+        let mov = board.parse_uci_move("g1f3").unwrap();
+        let undo = board.make_move_inplace(&mov).unwrap();
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 255 32768");
+        board.unmake_move(&mov, &undo);
+        assert_eq!(board.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 255 32768");
+    }
+
+    #[test]
+    fn zobrist_hash() {
+        // Nf3 Nf6 Ng1 Ng8 transposes back to the start position
+        let mut board = Board::standard();
+        for &(src, dest) in &[(6, 21), (62, 45), (21, 6), (45, 62)] {
+            board = board.make_move(&find_move(&board, src, dest)).unwrap();
+            assert_eq!(board.hash, board.compute_hash());
+        }
+        assert_eq!(board.hash, Board::standard().hash);
+        assert_ne!(board.hash, Board::from_fenstr(KIWIPETE_FEN).unwrap().hash);
+        // incremental keys are checked against the full recomputation on every node in debug builds
+        perft::run_for(Board::from_fenstr(KIWIPETE_FEN).unwrap(), 3);
+    }
+
+    fn find_move(board: &Board, src: u8, dest: u8) -> Move {
+        let mut move_list = MoveList::new();
+        board.move_list(board.player, &mut move_list);
+        let mov = *move_list.iter().find(|m| m.src() == src && m.dest() == dest).unwrap();
+        mov
+    }
+
+    #[test]
+    fn game_outcome() {
+        // knights out and back twice, the start position occurs for the third time
+        let mut game = Game::standard();
+        for _ in 0..2 {
+            assert!(!game.is_threefold());
+            for &(src, dest) in &[(6, 21), (62, 45), (21, 6), (45, 62)] {
+                let mov = find_move(game.board(), src, dest);
+                game.make_move(&mov).unwrap();
+            }
+        }
+        assert!(game.is_threefold());
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::ThreefoldRepetition)));
+
+        // fool's mate
+        let mut game = Game::standard();
+        for &(src, dest) in &[(13, 21), (52, 36), (14, 30), (59, 31)] {
+            let mov = find_move(game.board(), src, dest);
+            assert_eq!(game.outcome(), None);
+            game.make_move(&mov).unwrap();
+        }
+        assert_eq!(game.outcome(), Some(Outcome::Checkmate{ winner: BLACK }));
+
+        let mut game = Game::new(Board::from_fenstr("8/8/8/8/8/5k2/8/R6K w - - 99 80").unwrap());
+        game.make_move(&find_move(game.board(), 0, 1)).unwrap();
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::FiftyMoveRule)));
+
+        let game = Game::new(Board::from_fenstr("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap());
+        assert_eq!(game.outcome(), Some(Outcome::Draw(DrawReason::Stalemate)));
+
+        for &(fen, insufficient) in &[
+            ("8/8/4k3/8/8/3K4/8/8 w - - 0 1", true),
+            ("8/8/4k3/8/8/3KN3/8/8 w - - 0 1", true),
+            ("8/2b5/4k3/8/8/3KB3/8/8 w - - 0 1", true),
+            ("8/3b4/4k3/8/8/3KB3/8/8 w - - 0 1", false),
+            ("8/8/4k3/8/8/3KNN2/8/8 w - - 0 1", false),
+            ("8/8/4k3/8/8/3KP3/8/8 w - - 0 1", false),
+        ] {
+            let game = Game::new(Board::from_fenstr(fen).unwrap());
+            assert_eq!(game.is_insufficient_material(), insufficient, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn board_status() {
+        assert_eq!(Board::standard().status(), BoardStatus::Ongoing);
+        assert!(!Board::standard().in_check());
+
+        // fool's mate
+        let board = Board::from_fenstr("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert_eq!(board.checkers(), 1 << 31);
+        assert_eq!(board.status(), BoardStatus::Checkmate);
+
+        let board = Board::from_fenstr("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(board.status(), BoardStatus::Stalemate);
+
+        // pawn check from its right capture
+        let board = Board::from_fenstr("4k3/8/8/8/8/8/5p2/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.checkers(), 1 << 13);
+
+        // double check by rook and knight, only Kd1 and Kf1
+        let board = Board::from_fenstr("4r1k1/8/8/8/8/3n4/3R4/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.checkers(), (1 << 60) | (1 << 19));
+        let mut move_list = MoveList::new();
+        board.move_list(board.player, &mut move_list);
+        let mut dests: Vec<u8> = move_list.iter().map(|m| m.dest()).collect();
+        dests.sort();
+        assert_eq!(dests, vec![3, 5]);
+
+        // the king can't step back along the checking rook's file
+        let board = Board::from_fenstr("4k3/8/8/8/4r3/8/4K3/8 w - - 0 1").unwrap();
+        let mut move_list = MoveList::new();
+        board.move_list(board.player, &mut move_list);
+        assert!(move_list.iter().all(|m| m.dest() != 4));
+    }
+
+    #[test]
+    fn san() {
+        let san_list = |fen: &str| {
+            let board = Board::from_fenstr(fen).unwrap();
+            let mut move_list = MoveList::new();
+            board.move_list(board.player, &mut move_list);
+            move_list.iter().map(|m| m.to_san(&board)).collect::<Vec<String>>()
+        };
+        let moves = san_list(STARTPOS_FEN);
+        assert!(moves.contains(&"e4".to_string()));
+        assert!(moves.contains(&"Nf3".to_string()));
+
+        let moves = san_list("rnbqkbnr/pppp1ppp/8/8/4p3/5P2/PPPPP1PP/RNBQKBNR b KQkq - 0 2");
+        assert!(moves.contains(&"Qh4+".to_string()));
+        let moves = san_list("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2");
+        assert!(moves.contains(&"Qh4#".to_string()));
+        let moves = san_list("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2");
+        assert!(moves.contains(&"exd5".to_string()));
+
+        // file, rank and square disambiguation
+        let moves = san_list("4k3/8/8/R7/8/8/8/R3K3 w Q - 0 1");
+        assert!(moves.contains(&"R5a3".to_string()));
+        assert!(moves.contains(&"R1a3".to_string()));
+        assert!(moves.contains(&"Rb1".to_string()));
+        let moves = san_list("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1");
+        assert!(moves.contains(&"Nbd2".to_string()));
+        assert!(moves.contains(&"Nfd2".to_string()));
+        let moves = san_list("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1");
+        assert!(moves.contains(&"Qa1b2".to_string()));
+        assert!(moves.contains(&"Qcb2".to_string()));
+        assert!(moves.contains(&"Q3b2".to_string()));
+
+        // castling and promotions
+        let moves = san_list("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert!(moves.contains(&"O-O".to_string()));
+        assert!(moves.contains(&"O-O-O".to_string()));
+        assert!(moves.contains(&"b8=Q+".to_string()));
+        assert!(moves.contains(&"b8=N".to_string()));
+        assert!(moves.contains(&"bxa8=N".to_string()));
+        assert!(moves.contains(&"bxa8=Q+".to_string()));
+    }
+
+    #[test]
+    fn parse_moves() {
+        let board = Board::standard();
+        assert_eq!(board.parse_uci_move("e2e4").unwrap().to_san(&board), "e4");
+        assert_eq!(board.parse_san("Nf3").unwrap().to_uci(), "g1f3");
+        assert_eq!(board.parse_uci_move("e2e5"), Err(MoveParseError::Illegal("e2e5".to_string())));
+        assert_eq!(board.parse_uci_move("e2e"), Err(MoveParseError::Malformed("e2e".to_string())));
+        assert_eq!(board.parse_uci_move("e2e4x"), Err(MoveParseError::Malformed("e2e4x".to_string())));
+        assert_eq!(board.parse_san("Ke2"), Err(MoveParseError::Illegal("Ke2".to_string())));
+        assert_eq!(board.parse_san("Nf3g"), Err(MoveParseError::Malformed("Nf3g".to_string())));
+
+        let board = Board::from_fenstr("4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(board.parse_san("Nd2"), Err(MoveParseError::Ambiguous("Nd2".to_string())));
+        assert_eq!(board.parse_san("Nbd2").unwrap().src(), 1);
+
+        let board = Board::from_fenstr("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(board.parse_uci_move("b7b8n").unwrap().to_san(&board), "b8=N");
+        assert_eq!(board.parse_san("b8").map(|m| m.to_uci()), Err(MoveParseError::Illegal("b8".to_string())));
+        assert_eq!(board.parse_uci_move("e1c1").unwrap().to_san(&board), "O-O-O");
+        assert_eq!(board.parse_san("0-0").unwrap().to_uci(), "e1g1");
+
+        // every legal move survives both notations
+        for fen in &[STARTPOS_FEN, KIWIPETE_FEN] {
+            let board = Board::from_fenstr(fen).unwrap();
+            let mut move_list = MoveList::new();
+            board.move_list(board.player, &mut move_list);
+            for mov in move_list.iter() {
+                assert_eq!(board.parse_uci_move(&mov.to_uci()), Ok(*mov));
+                assert_eq!(board.parse_san(&mov.to_san(&board)), Ok(*mov));
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok( () )
+        }
+    }
+
+    impl SharedBuf {
+        fn take_lines(&self) -> Vec<String> {
+            let buf = std::mem::take(&mut *self.0.lock().unwrap());
+            String::from_utf8(buf).unwrap().lines().map(String::from).collect()
+        }
+
+        /// Lines written up to one starting with {prefix}. Fails instead of hanging if it doesn't come
+        fn lines_until(&self, prefix: &str) -> Vec<String> {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            let mut lines = Vec::new();
+            while !lines.last().map_or(false, |l: &String| l.starts_with(prefix)) {
+                assert!(Instant::now() < deadline, "no {} line within 10s, got {:?}", prefix, lines);
+                std::thread::sleep(Duration::from_millis(1));
+                lines.extend(self.take_lines());
+            }
+            lines
+        }
+    }
+
+    #[test]
+    fn uci_engine() {
+        let out = SharedBuf::default();
+        let mut engine = uci::UCIEngine::new(out.clone());
+        assert!(engine.handle("uci"));
+        assert_eq!(out.take_lines().last().map(String::as_str), Some("uciok"));
+        engine.handle("isready");
+        assert_eq!(out.take_lines(), vec!["readyok"]);
+        engine.handle("setoption name Hash value 64");
+        assert_eq!(engine.hash_mb(), 64);
+        engine.handle("setoption name EvalFile value /nonexistent/params.txt");
+        assert!(out.take_lines()[0].starts_with("info string can't load EvalFile"));
+        assert_eq!(engine.eval_params(), &EvalParams::default());
+
+        engine.handle("position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(engine.game().board().to_fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2");
+        engine.handle(&format!("position fen {} moves e1g1", KIWIPETE_FEN));
+        assert_eq!(engine.game().moves().len(), 1);
+        engine.handle("position startpos moves e2e5");
+        assert_eq!(out.take_lines(), vec!["info string Illegal move: e2e5"]);
+
+        engine.handle("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        engine.handle("go depth 1");
+        engine.handle("stop");
+        let lines = out.take_lines();
+        assert_eq!(lines.last().map(String::as_str), Some("bestmove 0000"));
+
+        // limits don't apply before depth 1 has a move
+        engine.handle("position startpos");
+        engine.handle("go nodes 1");
+        let lines = out.lines_until("bestmove");
+        assert!(lines.iter().any(|l| l.starts_with("info depth 1 ") && l.contains(" pv ")));
+        let best_move = lines.last().and_then(|l| l.strip_prefix("bestmove ")).unwrap();
+        assert!(Board::standard().parse_uci_move(best_move).is_ok());
+
+        engine.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        engine.handle("go depth 3");
+        let lines = out.lines_until("bestmove");
+        assert!(lines.iter().any(|l| l.starts_with("info depth ") && l.contains(" score mate 1 ") && l.ends_with("pv a1a8")));
+        assert_eq!(lines.last().map(String::as_str), Some("bestmove a1a8"));
+
+        engine.handle("position startpos");
+        engine.handle("go wtime 1000 btime 1000");
+        engine.handle("isready");
+        assert!(!engine.handle("quit"));
+        let lines = out.take_lines();
+        assert!(lines.contains(&"readyok".to_string()));
+        let best_move = lines.iter().find_map(|l| l.strip_prefix("bestmove ")).unwrap();
+        assert!(Board::standard().parse_uci_move(best_move).is_ok());
+
+        let params = uci::GoParams::from_tokens("wtime 300 btime 200 winc 5 movestogo 10 depth 4".split_whitespace());
+        assert_eq!(params, uci::GoParams{
+            wtime: Some(300), btime: Some(200), winc: Some(5), movestogo: Some(10), depth: Some(4),
+            ..Default::default()
+        });
+    }
+
+    fn search_fen(fen: &str, limits: SearchLimits) -> SearchResult {
+        let board = Board::from_fenstr(fen).unwrap();
+        Searcher::new(limits, Arc::new(AtomicBool::new(false)), TransTable::new(1)).search(&board, &[], |_| {})
+    }
+
+    #[test]
+    fn search() {
+        let depth = |depth| SearchLimits{ depth: Some(depth), ..Default::default() };
+
+        // back rank mate in 1
+        let result = search_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", depth(4));
+        assert_eq!(result.best_move.map(|m| m.to_uci()), Some("a1a8".to_string()));
+        assert_eq!(result.mate_in(), Some(1));
+        // mate in 2: Rb8+ Rxb8 Rxb8#
+        let result = search_fen("r6k/6pp/8/8/8/8/1R6/1R4K1 w - - 0 1", depth(4));
+        assert_eq!(result.mate_in(), Some(2));
+        // being mated
+        let result = search_fen("7k/8/6KQ/8/8/8/8/8 b - - 0 1", depth(4));
+        assert_eq!(result.mate_in(), Some(-1));
+        // win the hanging queen
+        let result = search_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", depth(3));
+        assert_eq!(result.best_move.map(|m| m.to_uci()), Some("d2d5".to_string()));
+        assert!(result.score > 400);
+        // the pawn is defended, quiescence sees the recapture past the horizon
+        let result = search_fen("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", depth(1));
+        assert_ne!(result.best_move.map(|m| m.to_uci()), Some("d1d5".to_string()));
+        assert!((600..=800).contains(&result.score));
+        // stalemate
+        let result = search_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", depth(3));
+        assert_eq!( (result.best_move, result.score), (None, 0) );
+
+        let board = Board::standard();
+        let result = search_fen(STARTPOS_FEN, depth(3));
+        assert_eq!(result.depth, 3);
+        assert_eq!(result.pv.first(), result.best_move.as_ref());
+        let mut pv_board = board;
+        for mov in result.pv.iter() {
+            pv_board = pv_board.make_move(mov).unwrap();
+        }
+
+        let result = search_fen(KIWIPETE_FEN, SearchLimits{ nodes: Some(5000), ..Default::default() });
+        assert!(result.best_move.is_some());
+        assert!(result.nodes <= 5000);
+
+        // black can only shuffle the king, so a repetition saves it from losing on material
+        let mut game = Game::new(Board::from_fenstr("7k/8/5KP1/8/8/8/8/R7 b - - 0 1").unwrap());
+        for mov_str in &["h8g8", "a1a2", "g8h8", "a2a1"] {
+            let mov = game.board().parse_uci_move(mov_str).unwrap();
+            game.make_move(&mov);
+        }
+        let mut searcher = Searcher::new(depth(1), Arc::new(AtomicBool::new(false)), TransTable::new(1));
+        assert!(searcher.search(game.board(), &[], |_| {}).score < -500);
+        assert_eq!(searcher.search(game.board(), game.history(), |_| {}).score, 0);
+
+        // stopped before the first iteration completes, still a legal move
+        let stop = Arc::new(AtomicBool::new(true));
+        let mut iters = 0;
+        let result = Searcher::new(SearchLimits::default(), stop, TransTable::new(1)).search(&board, &[], |_| iters += 1);
+        assert_eq!(iters, 1);
+        assert!(board.make_move(&result.best_move.unwrap()).is_some());
+    }
+
+    #[test]
+    fn capture_list() {
+        let fens = [
+            STARTPOS_FEN,
+            KIWIPETE_FEN,
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+        for fen in fens.iter() {
+            let board = Board::from_fenstr(fen).unwrap();
+            let mut all = MoveList::new();
+            board.move_list(board.player, &mut all);
+            let mut expected: Vec<String> = all.iter()
+                    .filter(|m| m.move_meta().is_capture() || matches!(m.move_meta(), MoveMeta::Promotion{ .. }))
+                    .map(|m| m.to_uci())
+                    .collect();
+            let mut captures = MoveList::new();
+            board.capture_list(board.player, &mut captures);
+            let mut found: Vec<String> = captures.iter().map(|m| m.to_uci()).collect();
+            expected.sort();
+            found.sort();
+            assert_eq!(found, expected, "{}", fen);
+        }
+        let board = Board::from_fenstr(KIWIPETE_FEN).unwrap();
+        let mut captures = MoveList::new();
+        board.capture_list(board.player, &mut captures);
+        assert_eq!(captures.iter().count(), 8);
+    }
+
+    #[test]
+    fn see() {
+        let see = |fen: &str, uci: &str| {
+            let board = Board::from_fenstr(fen).unwrap();
+            board.see(&board.parse_uci_move(uci).unwrap())
+        };
+        // undefended pawn
+        assert_eq!(see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), 100);
+        // pawn defended by a knight, recaptured by the queen behind
+        assert_eq!(see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"), -220);
+        // rook takes a defended rook
+        assert_eq!(see("4k3/4r3/8/8/4r3/8/8/4R1K1 w - - 0 1", "e1e4"), 0);
+        // doubled rooks win the exchange against a defended rook
+        assert_eq!(see("4k3/4r3/8/4r3/8/8/4R3/4R1K1 w - - 0 1", "e2e5"), 500);
+        // queen takes a pawn defended by a pawn
+        assert_eq!(see("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", "d1d5"), -800);
+        // the king can't recapture into the rook's attack
+        assert_eq!(see("8/8/8/3k4/3p4/8/3R4/3RK3 w - - 0 1", "d2d4"), 100);
+        assert_eq!(see("8/8/8/3k4/3p4/8/3R4/4K3 w - - 0 1", "d2d4"), -400);
+        // enpassant and promotion
+        assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+        assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q"), 1300);
+        assert_eq!(see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), -100);
+        // quiet moves onto attacked squares
+        assert_eq!(see(STARTPOS_FEN, "e2e4"), 0);
+        assert_eq!(see("4k3/8/8/2p5/8/8/2N5/4K3 w - - 0 1", "c2b4"), -320);
+
+        let board = Board::from_fenstr("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let mov = board.parse_uci_move("d1d5").unwrap();
+        assert!(board.see_ge(&mov, -800) && !board.see_ge(&mov, 0));
+    }
+
+    #[test]
+    fn attackers_to() {
+        let board = Board::from_fenstr("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1").unwrap();
+        let sq = |s: &str| utils::pos_from_str(s).unwrap();
+        let mask = |squares: &[&str]| squares.iter().fold(0, |acc, s| acc | utils::pos_mask(sq(s)));
+        let occupancy = !board.empty_mask();
+        assert_eq!(board.attackers_to(sq("e5"), occupancy), mask(&["d3", "e2", "d7", "f6"]));
+        // the queens behind the rook and bishop x-ray through once they are gone
+        let occupancy = occupancy & !mask(&["e2", "f6"]);
+        assert_eq!(board.attackers_to(sq("e5"), occupancy) & occupancy, mask(&["d3", "e1", "d7", "h8"]));
+        // pawns, kings and both colours
+        let occupancy = !board.empty_mask();
+        assert_eq!(board.attackers_to(sq("b3"), occupancy), mask(&["c2"]));
+        assert_eq!(board.attackers_to(sq("c2"), occupancy), mask(&["c1", "e2"]));
+        assert_eq!(board.attackers_to(sq("b6"), occupancy), mask(&["c7", "d7"]));
+
+        assert!(board.sq_attacked(sq("e5"), BLACK));
+        assert!(!board.sq_attacked(sq("d3"), WHITE));
+    }
+
+    #[test]
+    fn perft_divide() {
+        let divide = perft::divide(&Board::standard(), 3);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|(_, n)| n).sum::<u64>(), perft::PERFT_NODE_COUNT[3] as u64);
+        let nodes = |uci: &str| divide.iter().find(|(m, _)| m.to_uci() == uci).map(|(_, n)| *n);
+        assert_eq!(nodes("e2e4"), Some(600));
+        assert_eq!(nodes("g1f3"), Some(440));
+        assert_eq!(nodes("a2a3"), Some(380));
+
+        let stats = perft::stats(&Board::standard(), 3);
+        assert_eq!(stats, perft::PerftStats{ nodes: 8902, captures: 34, checks: 12, ..Default::default() });
+        assert_eq!(perft::stats(&Board::standard(), 0).nodes, 1);
+
+        let kiwipete = Board::from_fenstr(KIWIPETE_FEN).unwrap();
+        assert_eq!(perft::stats(&kiwipete, 1), perft::PerftStats{ nodes: 48, captures: 8, castles: 2, ..Default::default() });
+        // discovered and double checks, mates
+        let stats = perft::stats(&Board::from_fenstr("4k3/8/8/8/4N3/8/8/K3R3 w - - 0 1").unwrap(), 1);
+        assert_eq!((stats.checks, stats.discovery_checks, stats.double_checks), (8, 8, 2));
+        let stats = perft::stats(&Board::from_fenstr("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap(), 1);
+        assert_eq!((stats.checks, stats.checkmates), (1, 1));
+    }
+
+    #[test]
+    fn perft_parallel() {
+        for fen in &[STARTPOS_FEN, KIWIPETE_FEN] {
+            let board = Board::from_fenstr(fen).unwrap();
+            let expected = perft::run_for(board, 3);
+            for &(split_depth, threads) in &[(0, 1), (1, 4), (2, 3), (5, 2)] {
+                assert_eq!(perft::run_parallel(board, 3, split_depth, threads), expected, "{} {}", fen, split_depth);
+            }
+        }
+        // checkmated at the root, the plies below are missing like in run_for
+        let board = Board::from_fenstr("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert_eq!(perft::run_parallel(board, 2, 1, 2), perft::run_for(board, 2));
+    }
+
+    const PERFT_SUITE: &str = include_str!("../perft.epd");
+    // depths with more nodes than this are left to perft_suite_deep
+    const PERFT_SUITE_NODES: u64 = 500_000;
+
+    fn check_perft_suite(max_nodes: u64, count: fn(&Board, u16) -> u64) {
+        for entry in perft::parse_epd(PERFT_SUITE).unwrap() {
+            for &(depth, nodes) in entry.depths.iter().filter(|(_, n)| *n <= max_nodes) {
+                assert_eq!(count(&entry.board, depth), nodes, "{} depth {}", entry.fen, depth);
+            }
+        }
+    }
+
+    #[test]
+    fn perft_suite() {
+        let entries = perft::parse_epd(PERFT_SUITE).unwrap();
+        assert_eq!(entries[0].board, Board::standard());
+        assert_eq!(entries[0].depths[2], (3, 8902));
+        assert!(perft::parse_epd("8/8/8/8/8/8/8/8 w - - 0 1 ;D1 1").is_err());
+        assert!(perft::parse_epd(&format!("{} ;D1", STARTPOS_FEN)).is_err());
+        assert!(perft::parse_epd(&format!("{} ;1 20", STARTPOS_FEN)).is_err());
+        check_perft_suite(PERFT_SUITE_NODES, perft::count);
+        check_perft_suite(PERFT_SUITE_NODES, perft::count_bulk);
+    }
+
+    #[test]
+    fn legal_move_list() {
+        // enpassant would uncover a rook or queen along the rank
+        for fen in &["8/8/8/K2pP2r/8/8/8/7k w - d6 0 1", "8/8/8/8/k2Pp2Q/8/8/7K b - d3 0 1"] {
+            let board = Board::from_fenstr(fen).unwrap();
+            let mut move_list = MoveList::new();
+            board.move_list(board.player, &mut move_list);
+            assert!(move_list.iter().all(|mov| mov.move_meta() != MoveMeta::Enpassant), "{}", fen);
+        }
+        // the other pawn stays between the king and the rook, both captures are legal
+        let board = Board::from_fenstr("8/8/8/KPpP3r/8/8/8/7k w - c6 0 1").unwrap();
+        let mut move_list = MoveList::new();
+        board.move_list(board.player, &mut move_list);
+        assert_eq!(move_list.iter().filter(|mov| mov.move_meta() == MoveMeta::Enpassant).count(), 2);
+
+        // no generated move leaves the mover's king attacked
+        for entry in perft::parse_epd(PERFT_SUITE).unwrap() {
+            perft::run_for_each(entry.board, 2, |board| {
+                let mut move_list = MoveList::new();
+                board.move_list(board.player, &mut move_list);
+                for mov in move_list.iter() {
+                    let next_board = board.make_move(mov).unwrap();
+                    let king_pos = next_board.piece_bb(PieceType::King, board.player).trailing_zeros() as u8;
+                    assert!(!next_board.sq_attacked(king_pos, board.player), "{} {}", board.to_fen(), mov.to_uci());
+                }
+            });
+        }
+    }
+
+    // cargo test --release -- --ignored
+    #[test]
+    #[ignore]
+    fn perft_suite_deep() {
+        check_perft_suite(u64::MAX, perft::count_bulk);
+    }
+
+    #[test]
+    fn make_unmake() {
+        let fens = [
+            STARTPOS_FEN,
+            KIWIPETE_FEN,
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+        ];
+        for fen in fens.iter() {
+            let board = Board::from_fenstr(fen).unwrap();
+            let mut move_list = MoveList::new();
+            board.move_list(board.player, &mut move_list);
+            let mut inplace = board;
+            for mov in move_list.iter() {
+                let undo = inplace.make_move_inplace(mov);
+                assert_eq!(undo.map(|_| inplace), board.make_move(mov), "{} {}", fen, mov.to_uci());
+                if let Some(undo) = undo {
+                    inplace.unmake_move(mov, &undo);
+                }
+                assert_eq!(inplace, board, "{} {}", fen, mov.to_uci());
+            }
+        }
+        let mut board = Board::from_fenstr(KIWIPETE_FEN).unwrap();
+        assert_eq!(perft::count_unmake(&mut board, 3), perft::count(&board, 3));
+        assert_eq!(perft::count(&board, 3) as usize, perft::run_for(board, 3)[&3]);
+
+        // castling doesn't touch the other player's rights
+        let mut game = Game::new(Board::from_fenstr("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap());
+        let mov = game.board().parse_uci_move("e8g8").unwrap();
+        game.make_move(&mov);
+        assert_eq!(game.board().to_fen(), "r4rk1/8/8/8/8/8/8/R3K2R w KQ - 1 2");
+    }
+
+    #[test]
+    fn evaluation() {
+        let mut evaluator = Evaluator::default();
+        let tempo = evaluator.params().tempo;
+        let mut eval_fen = |fen: &str| evaluator.evaluate(&Board::from_fenstr(fen).unwrap());
+        // only the side to move differs
+        assert_eq!(eval_fen(STARTPOS_FEN), tempo.taper(eval::MAX_PHASE));
+        assert_eq!(eval::game_phase(&Board::standard()), eval::MAX_PHASE);
+        assert_eq!(eval::game_phase(&Board::from_fenstr("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap()), 0);
+        assert_eq!(Score::new(100, 200).taper(eval::MAX_PHASE), 100);
+        assert_eq!(Score::new(100, 200).taper(0), 200);
+
+        // scored for the side to move, the same for the colour flipped position
+        let knight_up = eval_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+        assert!(knight_up > 250);
+        let knight_phase = eval::PHASE_WEIGHTS[1];
+        assert_eq!(eval_fen("4k3/8/8/8/3N4/8/8/4K3 b - - 0 1"), -knight_up + 2*tempo.taper(knight_phase));
+        assert_eq!(eval_fen("4k3/8/8/3n4/8/8/8/4K3 b - - 0 1"), knight_up);
+        // knights on the rim are dim
+        assert!(eval_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1") < knight_up);
+        // centralize the king in the endgame, castle it in the middlegame
+        assert!(eval_fen("8/8/8/3k4/8/8/8/K7 w - - 0 1") < 0);
+        let castled = Board::from_fenstr("rnbq1rk1/ppppbppp/5n2/4p3/4P3/5N2/PPPPBPPP/RNBQ1RK1 w - - 6 5").unwrap();
+        assert!(evaluator.psqt(&castled).mg == 0);
+        let king_walk = Board::from_fenstr("rnbq1rk1/ppppbppp/5n2/4p3/2K1P3/5N2/PPPPBPPP/RNBQ3R w - - 6 5").unwrap();
+        assert!(evaluator.psqt(&king_walk).mg < -50);
+    }
+
+    #[test]
+    fn pawn_structure() {
+        let params = EvalParams::default();
+        let structure = |fen: &str| pawns::pawn_structure(&params, &Board::from_fenstr(fen).unwrap());
+        // doubled and isolated, both passed with nothing in the way
+        assert_eq!(structure("4k3/8/8/8/8/2P5/2P5/4K3 w - - 0 1").score,
+                params.doubled + params.isolated*2 + params.passed[1] + params.passed[2]);
+        // e7 and d5 stop each other from being passed, h7 is passed
+        let entry = structure("4k3/4p2p/8/3P4/8/8/8/4K3 w - - 0 1");
+        assert_eq!(entry.passed, utils::pos_mask(55));
+        assert_eq!(entry.score, params.isolated - params.isolated*2 - params.passed[1]);
+        // d3 can't be defended on its way to d4, which e5 holds. it defends e4
+        let entry = structure("4k3/8/8/4p3/4P3/3P4/8/4K3 w - - 0 1");
+        assert_eq!(entry.score, params.backward + params.connected - params.isolated);
+
+        // the pawn hash only covers the pawns
+        let board = Board::from_fenstr("4k3/8/8/4p3/4P3/3P4/8/4K3 w - - 0 1").unwrap();
+        let moved = Board::from_fenstr("3k4/8/8/4p3/4P3/3P4/8/2N1K3 b - - 0 1").unwrap();
+        assert_eq!(zobrist::pawn_hash(&board), zobrist::pawn_hash(&moved));
+        assert_ne!(zobrist::pawn_hash(&board), zobrist::pawn_hash(&Board::standard()));
+
+        // a passed pawn is worth more with its path clear
+        let free = Board::from_fenstr("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+        let blocked = Board::from_fenstr("3nk3/8/8/3P4/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut evaluator = Evaluator::default();
+        assert_eq!(evaluator.pawns(&free), params.isolated + params.passed[4] + params.passed_free[4]);
+        assert_eq!(evaluator.pawns(&blocked), params.isolated + params.passed[4]);
+        // cached structures are dropped with the weights they were scored with
+        let before = evaluator.evaluate(&free);
+        let mut params = params.clone();
+        params.passed[4] = params.passed[4] * 2;
+        evaluator.set_params(params);
+        assert!(evaluator.evaluate(&free) > before);
+    }
+
+    #[test]
+    fn mobility_king_safety() {
+        let evaluator = Evaluator::default();
+        let params = evaluator.params().clone();
+        let board = |fen: &str| Board::from_fenstr(fen).unwrap();
+        assert_eq!(evaluator.mobility(&Board::standard()), Score::default());
+        assert_eq!(evaluator.king_safety(&Board::standard()), Score::default());
+
+        // b5 is covered by the c6 pawn
+        let knight_i = eval::type_i(PieceType::Knight);
+        assert_eq!(evaluator.mobility(&board("4k3/8/2p5/8/3N4/8/8/4K3 w - - 0 1")), params.mobility[knight_i] * 7);
+        assert_eq!(evaluator.mobility(&board("4k3/8/8/8/8/8/8/N3K3 w - - 0 1")), params.mobility[knight_i] * 2);
+
+        // a full pawn shield against a king on open files
+        assert_eq!(evaluator.king_safety(&board("4k3/8/8/8/8/8/5PPP/6K1 w - - 0 1")),
+                params.pawn_shield * 3 - params.king_open_file * 3);
+        // the queen and rook attack around the black king, one attacker alone doesn't count
+        let attack = params.king_attack[eval::type_i(PieceType::Queen)] + params.king_attack[eval::type_i(PieceType::Rook)];
+        assert_eq!(evaluator.king_safety(&board("R5k1/5ppp/8/3Q4/8/8/8/6K1 b - - 0 1")),
+                params.king_open_file * 3 - params.pawn_shield * 3 - attack);
+        assert_eq!(evaluator.king_safety(&board("6k1/5ppp/8/3Q4/8/8/8/6K1 b - - 0 1")),
+                params.king_open_file * 3 - params.pawn_shield * 3);
+    }
+
+    #[test]
+    fn eval_trace() {
+        let mut evaluator = Evaluator::default();
+        let params = evaluator.params().clone();
+        for fen in &[STARTPOS_FEN, KIWIPETE_FEN, "4k3/4p2p/8/3P4/8/8/8/4K3 b - - 0 1"] {
+            let board = Board::from_fenstr(fen).unwrap();
+            let trace = evaluator.trace(&board);
+            assert_eq!(trace.eval, evaluator.evaluate(&board));
+            assert_eq!(trace.phase, eval::game_phase(&board));
+            assert_eq!(trace.term("material").unwrap().total(), evaluator.material(&board));
+            assert_eq!(trace.term("pawns").unwrap().total(), evaluator.pawns(&board));
+            assert_eq!(trace.term("king safety").unwrap().total(), evaluator.king_safety(&board));
+        }
+
+        let trace = evaluator.trace(&Board::standard());
+        let tempo = trace.term("tempo").unwrap();
+        assert_eq!((tempo.white, tempo.black), (params.tempo, Score::default()));
+        assert_eq!(trace.term("material").unwrap().white, trace.term("material").unwrap().black);
+        let json = trace.to_json();
+        assert!(json.starts_with(&format!("{{\"phase\": {}", eval::MAX_PHASE)));
+        for term in trace.terms.iter() {
+            assert!(json.contains(&format!("\"{}\": {{\"white\"", term.name)));
+            assert!(trace.to_string().contains(term.name));
+        }
+    }
+
+    #[test]
+    fn eval_params_file() {
+        let mut params = EvalParams{ tempo: Score::new(7, -3), ..Default::default() };
+        params.pst[1][27] = Score::new(-12, 40);
+        let text = params.to_string();
+        assert_eq!(text.parse::<EvalParams>(), Ok(params.clone()));
+        assert_eq!(format!("# tuned\n\n{}", text).parse::<EvalParams>(), Ok(params.clone()));
+        let weights = params.to_vec();
+        assert_eq!(weights.len(), params.fields().iter().map(|(_, field)| field.len()).sum::<usize>());
+        let mut loaded = EvalParams::default();
+        loaded.set_from(&weights);
+        assert_eq!(loaded, params);
+
+        assert_eq!("bogus 1 2".parse::<EvalParams>(), Err(ParamsError::UnknownField("bogus".to_string())));
+        assert_eq!("tempo 1 x".parse::<EvalParams>(), Err(ParamsError::InvalidValue("x".to_string())));
+        assert_eq!("tempo 1 2 3".parse::<EvalParams>(), Err(ParamsError::ValueCount{ field: "tempo", expected: 2, found: 3 }));
+        assert_eq!("tempo 1 2".parse::<EvalParams>(), Err(ParamsError::MissingField("material")));
+
+        // quiescence ends after the queen takes the hanging rook, in a position the evaluator scores the same
+        let board = Board::from_fenstr("4k3/8/8/3r4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(SearchLimits::default(), Arc::new(AtomicBool::new(false)), TransTable::new(1));
+        let (score, pv) = searcher.quiesce(&board);
+        assert_eq!(pv.iter().map(|mov| mov.to_uci()).collect::<Vec<String>>(), vec!["d2d5"]);
+        let leaf = board.make_move(&pv[0]).unwrap();
+        assert_eq!(score, -Evaluator::default().evaluate(&leaf));
+    }
+
+    #[test]
+    fn trans_table() {
+        let mut table = TransTable::new(1);
+        assert!(table.capacity().is_power_of_two());
+        assert!(table.probe(42).is_none());
+        let mov = Board::standard().parse_uci_move("e2e4").unwrap();
+        let entry = TTEntry{ best_move: mov, score: -35, depth: 4, bound: Bound::Lower, age: table.age() };
+        table.store(42, entry);
+        assert_eq!(table.probe(42), Some(entry));
+        table.store(42, TTEntry{ depth: 5, ..entry });
+        assert_eq!(table.probe(42).map(|e| e.depth), Some(5));
+
+        // a full bucket replaces its shallowest entry
+        let mut table = Table::<PerftEntry>::new(0);
+        assert_eq!(table.capacity(), tt::BUCKET_SLOTS);
+        for key in 1..=tt::BUCKET_SLOTS as u64 {
+            table.store(key, PerftEntry{ depth: key as u16, nodes: key });
+        }
+        table.store(100, PerftEntry{ depth: 9, nodes: 100 });
+        assert!(table.probe(1).is_none());
+        assert_eq!(table.probe(100).map(|e| e.nodes), Some(100));
+        assert_eq!(table.hashfull(), 1000);
+        table.clear();
+        assert_eq!(table.hashfull(), 0);
+
+        // key 0 is a key like any other, the pawn hash of a position without pawns
+        assert_eq!(zobrist::pawn_hash(&Board::from_fenstr("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap()), 0);
+        assert!(table.probe(0).is_none());
+        table.store(0, PerftEntry{ depth: 1, nodes: 7 });
+        assert_eq!(table.probe(0).map(|e| e.nodes), Some(7));
+
+        // entries from older searches go first
+        let old = TTEntry{ depth: 10, ..Default::default() };
+        assert!(old.priority(2) < TTEntry{ depth: 1, age: 2, ..Default::default() }.priority(2));
+
+        for fen in &[STARTPOS_FEN, KIWIPETE_FEN] {
+            let board = Board::from_fenstr(fen).unwrap();
+            assert_eq!(perft::run_for_hashed(board, 3, 1), perft::run_for(board, 3));
+        }
+    }
+
     #[test]
     fn test_uci_connect() {
         // assert!(false);
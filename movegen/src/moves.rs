@@ -3,7 +3,7 @@ use bitintr::{Blsr, Tzcnt};
 use std::fmt;
 
 use crate::pieces::{Piece, PieceType};
-use crate::board::{Square, BitBoard};
+use crate::board::{Board, BoardStatus, Square, BitBoard};
 use crate::utils;
 
 pub const MOVE_ILLEGAL:     u8 = 0;
@@ -79,6 +79,14 @@ impl MoveMeta {
     }
 }
 
+/// Which moves a move generator emits
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum GenType {
+    All,
+    /// Captures, enpassant and promotions only. For quiescence search
+    Captures,
+}
+
 pub const PIECE_MASK:       u8 = 0b1111_0000;
 pub const PIECE_MASK_SHIFT: u8  = 4;
 pub const MOVEMETA_MASK:    u8 = 0b0000_1111;
@@ -88,7 +96,7 @@ pub const SRC_MASK_SHIFT:   u16  = 8;
 pub const DEST_MASK:        u16 = 0b00000000_01111111;
 
 // Move(4bits piece _ 6bits source _ 6bits dest)
-#[derive(Copy)]
+#[derive(Copy, PartialEq, Eq, Debug)]
 pub struct Move{ meta: u8, srcdest: u16 }
 
 impl Move {
@@ -127,6 +135,76 @@ impl Move {
     pub fn is_invalid(&self) -> bool {
         self.meta == 0 && self.srcdest == 0
     }
+    /// UCI long algebraic notation. ex. e2e4, e7e8q, e1g1 for white short castle
+    pub fn to_uci(&self) -> String {
+        let dest = match self.move_meta() {
+            MoveMeta::Castle{ is_short } => if is_short { self.src() + 2 } else { self.src() - 2 },
+            _ => self.dest(),
+        };
+        let mut uci = format!("{}{}", utils::file_rank_str(self.src()), utils::file_rank_str(dest));
+        if let MoveMeta::Promotion{ is_capture: _, piece_type } = self.move_meta() {
+            uci.push_str(&piece_type.an().to_lowercase());
+        }
+        uci
+    }
+    /// Standard Algebraic Notation of this move, played from {board}.
+    /// ex. Nbd7, exd5, e8=Q+, O-O, Qh4#
+    pub fn to_san(&self, board: &Board) -> String {
+        let mut san = String::with_capacity(8);
+        let piece = self.piece();
+        let meta = self.move_meta();
+        match meta {
+            MoveMeta::Castle{ is_short } => san.push_str(if is_short { "O-O" } else { "O-O-O" }),
+            _ => {
+                san.push_str(piece.an());
+                if piece.piece_type == PieceType::Pawn {
+                    // pawn captures name the file the pawn left
+                    if meta.is_capture() {
+                        san.push(utils::file(self.src()));
+                    }
+                } else if piece.piece_type != PieceType::King {
+                    // other legal moves by the same piece type to the same square need disambiguation
+                    let mut move_list = MoveList::new();
+                    board.move_list(board.player, &mut move_list);
+                    let (file, rank) = utils::file_rank(self.src());
+                    let (mut ambiguous, mut same_file, mut same_rank) = (false, false, false);
+                    for other in move_list.iter()
+                                    .filter(|m| m.piece() == piece && m.dest() == self.dest() && m.src() != self.src()) {
+                        let (other_file, other_rank) = utils::file_rank(other.src());
+                        ambiguous = true;
+                        same_file |= other_file == file;
+                        same_rank |= other_rank == rank;
+                    }
+                    if ambiguous {
+                        let src_str = utils::file_rank_str(self.src());
+                        if !same_file {
+                            san.push_str(&src_str[..1]);
+                        } else if !same_rank {
+                            san.push_str(&src_str[1..]);
+                        } else {
+                            san.push_str(&src_str);
+                        }
+                    }
+                }
+                if meta.is_capture() {
+                    san.push('x');
+                }
+                san.push_str(&utils::file_rank_str(self.dest()));
+                if let MoveMeta::Promotion{ is_capture: _, piece_type } = meta {
+                    san.push('=');
+                    san.push_str(piece_type.an());
+                }
+            }
+        }
+        if let Some(next_board) = board.make_move(self) {
+            match next_board.status() {
+                BoardStatus::Checkmate => san.push('#'),
+                _ if next_board.in_check() => san.push('+'),
+                _ => {}
+            }
+        }
+        san
+    }
 }
 
 impl Clone for Move {
@@ -190,6 +268,14 @@ impl MoveList {
     pub fn iter(&self) -> impl Iterator<Item = &Move> {
         self.moves.iter()
     }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
 }
 
 pub struct BitPositions(pub BitBoard);
@@ -1,9 +1,16 @@
 
-use crate::board::Board;
-use crate::moves::MoveList;
+use std::ops::AddAssign;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::board::{Board, BoardStatus};
+use crate::moves::{Move, MoveList, MoveMeta};
+use crate::tt::{Table, PerftEntry};
+use crate::utils;
 use fnv::FnvHashMap;
+use std::error::Error;
 
-pub const PERFT_NODE_COUNT: [u32; 7] = [ 
+pub const PERFT_NODE_COUNT: [u32; 8] = [ 
     1,
     20,
     400,
@@ -11,8 +18,8 @@ pub const PERFT_NODE_COUNT: [u32; 7] = [
     197_281,
     4_865_609,
     119_060_324,
-    // 3_195_901_860, // Let's be reasonable people
-    // 84_998_978_956,
+    3_195_901_860,
+    // 84_998_978_956, // Let's be reasonable people
     // 2_439_530_234_167,
     // 69_352_859_712_417,
     // 2_097_651_003_696_806,
@@ -29,22 +36,34 @@ pub fn run(depth: u16) -> FnvHashMap<u16, usize> {
 }
 
 pub fn run_for(start_board: Board, depth: u16) -> FnvHashMap<u16, usize> {
+    run_for_each(start_board, depth, |_| {})
+}
+
+/// Same as `run_for`, but calls {visit} on every board reached
+pub fn run_for_each<F>(start_board: Board, depth: u16, mut visit: F) -> FnvHashMap<u16, usize>
+where F: FnMut(&Board) {
     // Note that using vec as a stack will traverse the tree in depth first
     // with last moves in the movelist explored first
     // If this technique is used in a-b search, the movelist will have to be
     // sorted worst->best for optimal prunage
-    let mut queue = Vec::<Board>::with_capacity(PERFT_NODE_COUNT[depth as usize] as usize);
+    // the stack only holds the unexplored siblings of the current line
+    let mut queue = Vec::<Board>::with_capacity(depth as usize * 64 + 1);
     let mut move_list = MoveList::new();
     let mut node_counts = FnvHashMap::with_capacity_and_hasher((depth+1) as usize, Default::default());
 
     queue.push(start_board);
 
     while let Some(board) = queue.pop() {
-        // increment node count
-        node_counts.entry(board.half_move_count).and_modify(|n| *n += 1).or_insert(1);
+        // check the incrementally updated hash in debug builds
+        debug_assert_eq!(board.hash, board.compute_hash(), "hash mismatch at {}", board.to_fen());
+        visit(&board);
+
+        // increment node count. plies are counted from the start board, which need not be move 1
+        let ply = board.half_move_count - start_board.half_move_count;
+        node_counts.entry(ply).and_modify(|n| *n += 1).or_insert(1);
         
         // don't insert new nodes if depth reached
-        if board.half_move_count == depth {
+        if ply == depth {
             continue;
         }
 
@@ -60,4 +79,261 @@ pub fn run_for(start_board: Board, depth: u16) -> FnvHashMap<u16, usize> {
 
     }
     node_counts
-}
\ No newline at end of file
+}
+
+/// Same as `run_for`, with the subtrees below ply {split_depth} shared out to {threads} threads.
+/// The plies above the split are counted on the calling thread
+pub fn run_parallel(start_board: Board, depth: u16, split_depth: u16, threads: usize) -> FnvHashMap<u16, usize> {
+    let split_depth = split_depth.min(depth);
+    let mut roots = Vec::new();
+    let mut node_counts = run_for_each(start_board, split_depth, |board| {
+        if board.half_move_count - start_board.half_move_count == split_depth {
+            roots.push(*board);
+        }
+    });
+    // the subtree roots are counted again by the threads
+    node_counts.remove(&split_depth);
+
+    // each thread takes the next unclaimed subtree until there are none left
+    let next_root = AtomicUsize::new(0);
+    let thread_counts: Vec<Vec<u64>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.max(1))
+                .map(|_| scope.spawn(|| {
+                    let mut counts = vec![0; (depth - split_depth + 1) as usize];
+                    while let Some(board) = roots.get(next_root.fetch_add(1, Ordering::Relaxed)) {
+                        count_plies(board, &mut counts);
+                    }
+                    counts
+                }))
+                .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    });
+
+    for counts in thread_counts {
+        for (ply, &nodes) in counts.iter().enumerate().filter(|(_, &n)| n > 0) {
+            *node_counts.entry(split_depth + ply as u16).or_insert(0) += nodes as usize;
+        }
+    }
+    node_counts
+}
+
+// Add the node count of each ply from {board} to {counts}, {board} itself being counts[0].
+// The last ply is bulk counted
+fn count_plies(board: &Board, counts: &mut [u64]) {
+    counts[0] += 1;
+    if counts.len() == 1 {
+        return
+    }
+    let mut move_list = MoveList::new();
+    board.move_list(board.player, &mut move_list);
+    if counts.len() == 2 {
+        counts[1] += move_list.len() as u64;
+        return
+    }
+    for mov in move_list.iter() {
+        let mut next_board = *board;
+        next_board.make_move_inplace(mov);
+        count_plies(&next_board, &mut counts[1..]);
+    }
+}
+
+/// Same as `run_for`, but caches subtree node counts in a {mb} megabyte table
+/// so transpositions are only counted once
+pub fn run_for_hashed(start_board: Board, depth: u16, mb: usize) -> FnvHashMap<u16, usize> {
+    let mut table = Table::<PerftEntry>::new(mb);
+    (0..=depth)
+            .map(|ply| (ply, count_hashed(&start_board, ply, &mut table) as usize))
+            .collect()
+}
+
+fn count_hashed(board: &Board, depth: u16, table: &mut Table<PerftEntry>) -> u64 {
+    if depth == 0 {
+        return 1
+    }
+    if let Some(entry) = table.probe(board.hash).filter(|entry| entry.depth == depth) {
+        return entry.nodes
+    }
+    let mut move_list = MoveList::new();
+    board.move_list(board.player, &mut move_list);
+    let nodes = move_list.iter()
+            .filter_map(|mov| board.make_move(mov))
+            .map(|next_board| count_hashed(&next_board, depth - 1, table))
+            .sum();
+    table.store(board.hash, PerftEntry{ depth, nodes });
+    nodes
+}
+
+/// perft({depth}), the number of positions {depth} plies from {board}, copying the board for every move
+pub fn count(board: &Board, depth: u16) -> u64 {
+    if depth == 0 {
+        return 1
+    }
+    let mut move_list = MoveList::new();
+    board.move_list(board.player, &mut move_list);
+    let mut nodes = 0;
+    for mov in move_list.iter() {
+        let mut next_board = *board;
+        next_board.make_move_inplace(mov);
+        nodes += count(&next_board, depth - 1);
+    }
+    nodes
+}
+
+/// Same as `count`, but the moves one ply above the leaves are counted instead of played.
+/// Relies on `Board::move_list` only generating legal moves
+pub fn count_bulk(board: &Board, depth: u16) -> u64 {
+    if depth == 0 {
+        return 1
+    }
+    let mut move_list = MoveList::new();
+    board.move_list(board.player, &mut move_list);
+    if depth == 1 {
+        return move_list.len() as u64
+    }
+    let mut nodes = 0;
+    for mov in move_list.iter() {
+        let mut next_board = *board;
+        next_board.make_move_inplace(mov);
+        nodes += count_bulk(&next_board, depth - 1);
+    }
+    nodes
+}
+
+/// Same as `count`, but plays and takes back every move on {board}
+pub fn count_unmake(board: &mut Board, depth: u16) -> u64 {
+    if depth == 0 {
+        return 1
+    }
+    let mut move_list = MoveList::new();
+    board.move_list(board.player, &mut move_list);
+    let mut nodes = 0;
+    for mov in move_list.iter() {
+        if let Some(undo) = board.make_move_inplace(mov) {
+            nodes += count_unmake(board, depth - 1);
+            board.unmake_move(mov, &undo);
+        }
+    }
+    nodes
+}
+
+/// perft({depth}) under each legal move from {board}, in move generation order
+pub fn divide(board: &Board, depth: u16) -> Vec<(Move, u64)> {
+    let mut move_list = MoveList::new();
+    board.move_list(board.player, &mut move_list);
+    move_list.iter()
+            .filter_map(|mov| board.make_move(mov).map(|next_board| (*mov, next_board)))
+            .map(|(mov, next_board)| (mov, count_bulk(&next_board, depth.saturating_sub(1))))
+            .collect()
+}
+
+/// Counts of the moves leading to the positions at a perft depth,
+/// the columns of the Chess Programming Wiki perft results tables
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct PerftStats {
+    pub nodes: u64,
+    pub captures: u64,
+    pub enpassants: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    /// Checks given by a piece other than the one that moved
+    pub discovery_checks: u64,
+    pub double_checks: u64,
+    pub checkmates: u64,
+}
+
+impl PerftStats {
+    /// Count {mov}, played from {board} to reach {next_board}
+    fn add_move(&mut self, board: &Board, mov: &Move, next_board: &Board) {
+        self.nodes += 1;
+        let meta = mov.move_meta();
+        self.captures += meta.is_capture() as u64;
+        // the squares of the moved pieces, after the move
+        let moved_mask = match meta {
+            MoveMeta::Enpassant => { self.enpassants += 1; mov.dest_bb() },
+            MoveMeta::Castle{ is_short } => { self.castles += 1; utils::rook_castle(board.player, is_short) },
+            MoveMeta::Promotion{ .. } => { self.promotions += 1; mov.dest_bb() },
+            _ => mov.dest_bb(),
+        };
+        let checkers = next_board.checkers();
+        if checkers > 0 {
+            self.checks += 1;
+            self.discovery_checks += (checkers & !moved_mask > 0) as u64;
+            self.double_checks += (utils::n_set_bits(checkers) > 1) as u64;
+            self.checkmates += (next_board.status() == BoardStatus::Checkmate) as u64;
+        }
+    }
+}
+
+impl AddAssign for PerftStats {
+    fn add_assign(&mut self, other: Self) {
+        self.nodes += other.nodes;
+        self.captures += other.captures;
+        self.enpassants += other.enpassants;
+        self.castles += other.castles;
+        self.promotions += other.promotions;
+        self.checks += other.checks;
+        self.discovery_checks += other.discovery_checks;
+        self.double_checks += other.double_checks;
+        self.checkmates += other.checkmates;
+    }
+}
+
+/// perft({depth}) with the stats of the moves into the last ply
+pub fn stats(board: &Board, depth: u16) -> PerftStats {
+    let mut stats = PerftStats::default();
+    if depth == 0 {
+        stats.nodes = 1;
+    } else {
+        add_stats(board, depth, &mut stats);
+    }
+    stats
+}
+
+fn add_stats(board: &Board, depth: u16, stats: &mut PerftStats) {
+    let mut move_list = MoveList::new();
+    board.move_list(board.player, &mut move_list);
+    for mov in move_list.iter() {
+        if let Some(next_board) = board.make_move(mov) {
+            if depth == 1 {
+                stats.add_move(board, mov, &next_board);
+            } else {
+                add_stats(&next_board, depth - 1, stats);
+            }
+        }
+    }
+}
+
+/// A position of a perft suite with its expected node counts
+#[derive(Clone, Debug)]
+pub struct EpdEntry {
+    pub fen: String,
+    pub board: Board,
+    /// (depth, perft(depth)) pairs
+    pub depths: Vec<(u16, u64)>,
+}
+
+/// Parse a perft suite in the EPD format `<fen> ;D1 <nodes> ;D2 <nodes> ...`.
+/// Blank lines and lines starting with # are skipped
+pub fn parse_epd(suite: &str) -> Result<Vec<EpdEntry>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    for line in suite.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or("").trim().to_string();
+        let board = Board::from_fenstr(&fen)?;
+        let mut depths = Vec::new();
+        for field in fields {
+            let mut tokens = field.split_whitespace();
+            let depth = tokens.next()
+                    .and_then(|d| d.strip_prefix('D'))
+                    .and_then(|d| d.parse::<u16>().ok())
+                    .ok_or_else(|| format!("expected D<depth> in: {}", field))?;
+            let nodes = tokens.next()
+                    .and_then(|n| n.parse::<u64>().ok())
+                    .ok_or_else(|| format!("expected a node count in: {}", field))?;
+            depths.push((depth, nodes));
+        }
+        entries.push(EpdEntry{ fen, board, depths });
+    }
+    Ok( entries )
+}
@@ -2,7 +2,7 @@ use std::iter;
 use hdf5;
 
 use crate::board::{BoardState, BitBoard, rank_bb, Direction};
-use crate::moves::{Move, MoveMeta, BitPositions, MoveList};
+use crate::moves::{Move, MoveMeta, BitPositions, MoveList, GenType};
 use crate::utils;
 
 pub const WHITE: bool = true;
@@ -153,6 +153,17 @@ impl PieceType {
             _ => PieceType::Invalid,    // 0b0000
         }
     }
+    /// Material value in centipawns. The king has none
+    pub fn cp_value(&self) -> i32 {
+        match self {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 320,
+            PieceType::Bishop => 330,
+            PieceType::Rook => 500,
+            PieceType::Queen => 900,
+            PieceType::King | PieceType::Invalid => 0,
+        }
+    }
     pub fn an(&self) -> &'static str {
         match self {
             PieceType::Pawn => "",
@@ -178,7 +189,7 @@ const PIECETYPE_BITS: u8 = 3;
 const PIECETYPE_MASK: u8 = 0b0111;
 
 // Piece(0b0000_1xxx) for white pieces. Piece(0b0000_0xxx) for black pieces
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Piece {
     pub piece_type: PieceType,
     pub player: bool 
@@ -239,7 +250,7 @@ impl Piece {
                 // forward right capture
                 let dir = if forward { Direction::NE } else { Direction::SW };
                 let cp_r_dest = utils::slide(*piece_mask, 1, &dir);
-                if cp_l_dest & king_mask > 0 {
+                if cp_r_dest & king_mask > 0 {
                     check_mask |= utils::slide(*king_mask, 1, &dir.opp())
                 }
                 (cp_l_dest | cp_r_dest, check_mask)
@@ -325,7 +336,8 @@ impl Piece {
         
         match self.piece_type {
             PieceType::Pawn => {
-                pawn_moves(forward, &piece_mask, &utils::ONES, &empty, &oppnt_mask, &board.enp_target, self, move_list);
+                let masks = PawnMasks{ valid: utils::ONES, empty, opp: oppnt_mask, enp_target: board.enp_target, enp_pinned: 0 };
+                pawn_moves(forward, piece_mask, &masks, true, self, move_list);
             },
             PieceType::Knight => {
                 for knight_pos in BitPositions(*piece_mask) {
@@ -419,8 +431,7 @@ impl Piece {
         }
     }                   
 
-    /// Finds legal (almost) moves for this piece. Returns the piece(s) attack mask
-    /// Only the corner case of enpassant capture leading to a check along the 4th rank is illegal
+    /// Finds the legal moves for this piece
     pub fn move_list(&self, piece_mask: &BitBoard, board_state: &BoardState, move_list: &mut MoveList) {
         let board = board_state.board;
 
@@ -429,8 +440,10 @@ impl Piece {
 
         let _player_mask = board.player_mask(forward);
         let oppnt_mask = board.player_mask(!forward);
-        
-        
+        let quiets = board_state.gen_type == GenType::All;
+        // destinations of non capturing moves
+        let quiet_mask = if quiets { empty } else { 0 };
+
         match self.piece_type {
             PieceType::Pawn => {
 
@@ -439,16 +452,29 @@ impl Piece {
                 // Probably not efficient
                 let pinned_pawns = piece_mask & board_state.pinned_mask;
                 let piece_mask = piece_mask & !board_state.pinned_mask;
+                // use the check mask as the valid mask
+                let valid = if board_state.opp_check_mask == &0 { utils::ONES } else { *board_state.opp_check_mask };
+                let masks = PawnMasks{
+                    valid,
+                    empty,
+                    opp: oppnt_mask,
+                    enp_target: board.enp_target,
+                    // pawns that can't capture enpassant without exposing the king along the rank
+                    enp_pinned: board.enp_pinned(forward),
+                };
 
                 for pawn_pos in BitPositions(pinned_pawns) {
                     if let Some(ray) = board_state.pinned_pieces.get(&pawn_pos) {
-                        let valid_mask = if ray == &0 { &utils::ONES } else { ray };
-                        pawn_moves(forward, &piece_mask, valid_mask, &empty, &oppnt_mask, &board.enp_target, self, move_list);
+                        let mut valid_mask = if ray == &0 { utils::ONES } else { *ray };
+                        // a pinned pawn can still only block or capture a checker
+                        if *(board_state.opp_check_mask) > 0 {
+                            valid_mask &= board_state.opp_check_mask;
+                        }
+                        let pawn_mask = utils::pos_mask(pawn_pos);
+                        pawn_moves(forward, &pawn_mask, &PawnMasks{ valid: valid_mask, ..masks }, quiets, self, move_list);
                     }
                 }
-                // use the check mask as the valid mask
-                let valid_mask = if board_state.opp_check_mask == &0 { &utils::ONES } else { board_state.opp_check_mask };
-                pawn_moves(forward, &piece_mask, valid_mask, &empty, &oppnt_mask, &board.enp_target, self, move_list);
+                pawn_moves(forward, &piece_mask, &masks, quiets, self, move_list);
             },
             PieceType::Knight => {
                 for knight_pos in BitPositions(*piece_mask) {
@@ -460,7 +486,7 @@ impl Piece {
                     if *(board_state.opp_check_mask) > 0 {
                         attack_mask &= board_state.opp_check_mask;
                     }
-                    let ncp_dest = attack_mask & empty;
+                    let ncp_dest = attack_mask & quiet_mask;
                     let cp_dest = attack_mask & oppnt_mask;
 
                     let meta = MoveMeta::Quiet;
@@ -480,7 +506,7 @@ impl Piece {
                     if *(board_state.opp_check_mask) > 0 {
                         attack_mask &= board_state.opp_check_mask;
                     }
-                    let ncp_dest = attack_mask & empty;
+                    let ncp_dest = attack_mask & quiet_mask;
                     let cp_dest = attack_mask & oppnt_mask;
 
                     let meta = MoveMeta::Quiet;
@@ -498,7 +524,7 @@ impl Piece {
                     if *(board_state.opp_check_mask) > 0 {
                         attack_mask &= board_state.opp_check_mask;
                     }
-                    let ncp_dest = attack_mask & empty;
+                    let ncp_dest = attack_mask & quiet_mask;
                     let cp_dest = attack_mask & oppnt_mask;
 
                     let meta = MoveMeta::Quiet;
@@ -517,7 +543,7 @@ impl Piece {
                     if *(board_state.opp_check_mask) > 0 {
                         attack_mask &= board_state.opp_check_mask;
                     }
-                    let ncp_dest = attack_mask & empty;
+                    let ncp_dest = attack_mask & quiet_mask;
                     let cp_dest = attack_mask & oppnt_mask;
     
                     let meta = MoveMeta::Quiet;
@@ -532,11 +558,11 @@ impl Piece {
                     // clear attacked squares
                     let attack_mask = attack_mask & !board_state.opp_attack_mask;
 
-                    let ncp_dest = attack_mask & empty;
+                    let ncp_dest = attack_mask & quiet_mask;
                     let cp_dest = attack_mask & oppnt_mask;
 
                     // can only castle while not in check
-                    if *(board_state.opp_check_mask) == 0 {
+                    if quiets && *(board_state.opp_check_mask) == 0 {
                         // check short castle rights
                         if board.castle_rights(forward, true) {
                             // check that opp does not attack the squares the king will travel over
@@ -580,16 +606,27 @@ impl std::fmt::Display for Piece {
     }
 }
 
+/// Board masks the pawn moves are generated from
+#[derive(Copy, Clone)]
+struct PawnMasks {
+    /// Destinations have to be on it: the pin ray and/or the check mask
+    valid: BitBoard,
+    empty: BitBoard,
+    opp: BitBoard,
+    enp_target: u8,
+    /// Pawns that can't capture enpassant without exposing their king along the rank
+    enp_pinned: BitBoard,
+}
+
 fn pawn_moves(
         forward: bool, 
         piece_mask: &BitBoard, 
-        valid_mask: &BitBoard, 
-        empty: &BitBoard, 
-        opp_mask: &BitBoard,
-        enp_target: &u8,
+        masks: &PawnMasks,
+        quiets: bool,
         self_: &Piece,
         move_list: &mut MoveList
     ) {
+    let PawnMasks{ valid: valid_mask, empty, opp: opp_mask, enp_target, enp_pinned } = *masks;
 
     let rank7 = if forward { rank_bb::SEVEN } else { rank_bb::TWO };
     let rank8 = if forward { rank_bb::EIGHT } else { rank_bb::ONE };
@@ -639,24 +676,28 @@ fn pawn_moves(
     // enpassant capture
     let mut cp_enp = 0;
     let mut cp_enp_dest = 0;
-    if *enp_target != 0 &&
-        *valid_mask == utils::ONES  // can't enpassant capture to block a check or when pinned
-        {
-            cp_enp_dest = if forward { enp_target+8 } else { enp_target-8 };
-            let dest_mask = utils::pos_mask(cp_enp_dest);
+    if enp_target != 0 {
+        cp_enp_dest = if forward { enp_target+8 } else { enp_target-8 };
+        let dest_mask = utils::pos_mask(cp_enp_dest);
+        // the capture has to land on the pin ray or block the check, or take the checking pawn
+        if (dest_mask | utils::pos_mask(enp_target)) & valid_mask > 0 {
             cp_enp = if forward { 
                 utils::slide(dest_mask, 1, &Direction::SW) |
                 utils::slide(dest_mask, 1, &Direction::SE)
              } else {
                 utils::slide(dest_mask, 1, &Direction::NW) |
                 utils::slide(dest_mask, 1, &Direction::NE)
-             } & piece_mask;
+             } & piece_mask & !enp_pinned;
         }
+    }
 
 
-    let meta = MoveMeta::Quiet;
-    move_list.push_from_forpiece( self_, &meta, BitPositions(pp1 & !rank7), BitPositions(pp1_dest & !rank8) );
-    move_list.push_from_forpiece( self_, &meta, BitPositions(pp2), BitPositions(pp2_dest) );
+    // pushes that don't promote
+    if quiets {
+        let meta = MoveMeta::Quiet;
+        move_list.push_from_forpiece( self_, &meta, BitPositions(pp1 & !rank7), BitPositions(pp1_dest & !rank8) );
+        move_list.push_from_forpiece( self_, &meta, BitPositions(pp2), BitPositions(pp2_dest) );
+    }
     let meta = MoveMeta::Capture;
     move_list.push_from_forpiece( self_, &meta, BitPositions(cp_l & !rank7), BitPositions(cp_l_dest & !rank8) );
     move_list.push_from_forpiece( self_, &meta, BitPositions(cp_r & !rank7), BitPositions(cp_r_dest & !rank8) );
@@ -0,0 +1,386 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::board::Board;
+use crate::moves::{Move, MoveList, MoveMeta};
+use crate::eval::Evaluator;
+use crate::pieces::PieceType;
+use crate::tt::{TransTable, TTEntry, Bound};
+
+pub const INF: i32 = 30_000;
+/// Score of being checkmated at the root. Mate in n plies scores MATE - n
+pub const MATE: i32 = 29_000;
+pub const MAX_DEPTH: u16 = 64;
+
+// how often the clock and stop flag are polled
+const POLL_NODES: u64 = 1024;
+// captures that can't bring the score within this of alpha are skipped in quiescence
+const DELTA_MARGIN: i32 = 200;
+
+/// When to stop searching. Unset limits don't apply, and none apply before depth 1 has found a move
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchLimits {
+    pub depth: Option<u16>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+/// Result of the last completed iteration
+#[derive(Clone, Debug, Default)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    /// Centipawns from the side to move's view
+    pub score: i32,
+    pub depth: u16,
+    pub nodes: u64,
+    pub time: Duration,
+    /// Principal variation, starting with best_move
+    pub pv: Vec<Move>,
+}
+
+impl SearchResult {
+    /// Moves until mate, negative when being mated. None if the score is not a mate score
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.score.abs() > MATE - MAX_DEPTH as i32 * 2 {
+            let plies = MATE - self.score.abs();
+            Some( self.score.signum() * (plies + 1) / 2 )
+        } else {
+            None
+        }
+    }
+}
+
+/// Negamax alpha-beta search with iterative deepening
+pub struct Searcher {
+    limits: SearchLimits,
+    stop: Arc<AtomicBool>,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+    // limits are ignored until depth 1 has a move to play
+    can_stop: bool,
+    // hashes of the game and the current search line for repetition detection
+    path: Vec<u64>,
+    tt: TransTable,
+    eval: Evaluator,
+}
+
+impl Searcher {
+    /// {stop} can be set from another thread to end the search early.
+    /// {tt} can be kept between searches of the same game with `into_table`
+    pub fn new(limits: SearchLimits, stop: Arc<AtomicBool>, tt: TransTable) -> Self {
+        Searcher {
+            limits,
+            stop,
+            start: Instant::now(),
+            nodes: 0,
+            stopped: false,
+            can_stop: true,
+            path: Vec::new(),
+            tt,
+            eval: Evaluator::default(),
+        }
+    }
+
+    pub fn into_table(self) -> TransTable {
+        self.tt
+    }
+
+    /// Evaluate positions with {eval} instead of the default weights
+    pub fn set_evaluator(&mut self, eval: Evaluator) {
+        self.eval = eval;
+    }
+
+    /// Quiescence search of {board} alone. Returns its score from the side to move's view
+    /// and the captures leading to the quiet position the score comes from
+    pub fn quiesce(&mut self, board: &Board) -> (i32, Vec<Move>) {
+        self.start = Instant::now();
+        self.nodes = 0;
+        self.stopped = false;
+        self.can_stop = true;
+        let mut pv = Vec::new();
+        let score = self.qsearch(board, -INF, INF, 0, &mut pv);
+        (score, pv)
+    }
+
+    /// Search {board}. {history} holds the hashes of the game positions played before it.
+    /// Calls {on_iter} after each completed depth
+    pub fn search<F>(&mut self, board: &Board, history: &[u64], mut on_iter: F) -> SearchResult
+    where F: FnMut(&SearchResult) {
+        self.start = Instant::now();
+        self.nodes = 0;
+        self.stopped = false;
+        self.can_stop = false;
+        self.tt.new_age();
+
+        let mut result = SearchResult::default();
+        let max_depth = self.limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        for depth in 1..=max_depth {
+            if self.can_stop && self.stop.load(Ordering::Relaxed) {
+                break;
+            }
+            self.path.clear();
+            self.path.extend_from_slice(history);
+            let mut pv = Vec::new();
+            let score = self.negamax(board, depth, -INF, INF, 0, &mut pv);
+            // a partial iteration is dropped for the last complete one
+            if self.stopped {
+                break;
+            }
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                score,
+                depth,
+                nodes: self.nodes,
+                time: self.start.elapsed(),
+                pv: pv.clone(),
+            };
+            on_iter(&result);
+            // depth 1 searches every root move, so a partial later iteration still has depth 1's move
+            self.can_stop = true;
+            if self.stopped || result.best_move.is_none() || result.mate_in().is_some() {
+                break;
+            }
+        }
+        result.nodes = self.nodes;
+        result.time = self.start.elapsed();
+        result
+    }
+
+    fn check_limits(&mut self) {
+        if !self.can_stop {
+            return
+        }
+        if self.limits.nodes.map_or(false, |n| self.nodes >= n) {
+            self.stopped = true;
+        }
+        if self.nodes % POLL_NODES == 0 && (
+            self.stop.load(Ordering::Relaxed) ||
+            self.limits.time.map_or(false, |t| self.start.elapsed() >= t)
+        ) {
+            self.stopped = true;
+        }
+    }
+
+    /// The position repeats one since the last capture or pawn move
+    fn is_repetition(&self, board: &Board) -> bool {
+        self.path.iter()
+                .rev()
+                .take(board.fifty_move_count as usize + 1)
+                .skip(2)
+                .step_by(2)
+                .any(|&hash| hash == board.hash)
+    }
+
+    fn negamax(&mut self, board: &Board, depth: u16, mut alpha: i32, beta: i32, ply: u16, pv: &mut Vec<Move>) -> i32 {
+        self.nodes += 1;
+        self.check_limits();
+        if self.stopped {
+            return 0
+        }
+        self.path.push(board.hash);
+        let score = self.negamax_moves(board, depth, &mut alpha, beta, ply, pv);
+        self.path.pop();
+        score
+    }
+
+    fn negamax_moves(&mut self, board: &Board, depth: u16, alpha: &mut i32, beta: i32, ply: u16, pv: &mut Vec<Move>) -> i32 {
+        if ply > 0 && (board.fifty_move_count >= 100 || self.is_repetition(board)) {
+            return 0
+        }
+        if depth == 0 {
+            return self.qsearch(board, *alpha, beta, ply, pv)
+        }
+
+        let entry = self.tt.probe(board.hash);
+        let tt_move = entry.map(|entry| entry.best_move).filter(|mov| !mov.is_invalid());
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth as u16 >= depth) {
+            let score = score_from_tt(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= *alpha,
+            };
+            if cutoff {
+                pv.clear();
+                pv.extend(tt_move);
+                return score
+            }
+        }
+
+        let mut move_list = MoveList::new();
+        board.move_list(board.player, &mut move_list);
+        let mut moves: Vec<Move> = move_list.iter().copied().collect();
+        moves.sort_by_key(|mov| -order_score(board, mov, tt_move));
+
+        let alpha_start = *alpha;
+        let mut best = -INF;
+        let mut best_move = Move::invalid();
+        let mut has_moves = false;
+        let mut child_pv = Vec::new();
+        for mov in moves.iter() {
+            let next_board = match board.make_move(mov) {
+                Some(next_board) => next_board,
+                None => continue,
+            };
+            has_moves = true;
+            child_pv.clear();
+            let score = -self.negamax(&next_board, depth - 1, -beta, -*alpha, ply + 1, &mut child_pv);
+            if self.stopped {
+                return best.max(0)
+            }
+            if score > best {
+                best = score;
+                best_move = *mov;
+                if score > *alpha {
+                    *alpha = score;
+                    pv.clear();
+                    pv.push(*mov);
+                    pv.extend_from_slice(&child_pv);
+                }
+                if score >= beta {
+                    break;
+                }
+            }
+        }
+        if !has_moves {
+            // checkmated, prefer the longest mate. or stalemate
+            return if board.in_check() { -MATE + ply as i32 } else { 0 }
+        }
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best > alpha_start {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(board.hash, TTEntry{
+            best_move,
+            score: score_to_tt(best, ply),
+            depth: depth as u8,
+            bound,
+            age: self.tt.age(),
+        });
+        best
+    }
+
+    /// Search captures and promotions until the position is quiet, so the evaluation
+    /// isn't taken in the middle of an exchange. In check every evasion is searched
+    fn qsearch(&mut self, board: &Board, mut alpha: i32, beta: i32, ply: u16, pv: &mut Vec<Move>) -> i32 {
+        pv.clear();
+        let in_check = board.in_check();
+        let stand_pat = self.eval.evaluate(board);
+        let mut best = -INF;
+        if !in_check {
+            // the side to move can decline every capture
+            if stand_pat >= beta {
+                return stand_pat
+            }
+            alpha = alpha.max(stand_pat);
+            best = stand_pat;
+        }
+
+        let mut move_list = MoveList::new();
+        if in_check {
+            board.move_list(board.player, &mut move_list);
+        } else {
+            board.capture_list(board.player, &mut move_list);
+        }
+        let mut moves: Vec<Move> = move_list.iter().copied().collect();
+        moves.sort_by_key(|mov| -order_score(board, mov, None));
+
+        let mut has_moves = false;
+        let mut child_pv = Vec::new();
+        for mov in moves.iter() {
+            if !in_check && !is_promotion(mov) && stand_pat + capture_value(board, mov) + DELTA_MARGIN < alpha {
+                continue;
+            }
+            // captures that lose material in the exchange
+            if !in_check && !board.see_ge(mov, 0) {
+                continue;
+            }
+            let next_board = match board.make_move(mov) {
+                Some(next_board) => next_board,
+                None => continue,
+            };
+            has_moves = true;
+            self.nodes += 1;
+            self.check_limits();
+            if self.stopped {
+                return best.max(0)
+            }
+            let score = -self.qsearch(&next_board, -beta, -alpha, ply + 1, &mut child_pv);
+            if self.stopped {
+                return best.max(0)
+            }
+            if score > best {
+                best = score;
+                if score > alpha {
+                    pv.clear();
+                    pv.push(*mov);
+                    pv.extend_from_slice(&child_pv);
+                }
+                if score >= beta {
+                    break;
+                }
+                alpha = alpha.max(score);
+            }
+        }
+        if in_check && !has_moves {
+            return -MATE + ply as i32
+        }
+        best
+    }
+}
+
+// Mate scores are stored relative to the node, so they stay right when reached at another ply
+fn score_to_tt(score: i32, ply: u16) -> i16 {
+    let score = if score > MATE - MAX_DEPTH as i32 * 2 {
+        score + ply as i32
+    } else if score < -MATE + MAX_DEPTH as i32 * 2 {
+        score - ply as i32
+    } else {
+        score
+    };
+    score as i16
+}
+
+fn score_from_tt(score: i16, ply: u16) -> i32 {
+    let score = score as i32;
+    if score > MATE - MAX_DEPTH as i32 * 2 {
+        score - ply as i32
+    } else if score < -MATE + MAX_DEPTH as i32 * 2 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+fn is_promotion(mov: &Move) -> bool {
+    matches!(mov.move_meta(), MoveMeta::Promotion{ .. })
+}
+
+/// Value of the piece {mov} captures
+fn capture_value(board: &Board, mov: &Move) -> i32 {
+    match mov.move_meta() {
+        MoveMeta::Enpassant => PieceType::Pawn.cp_value(),
+        _ => board.piece_at(mov.dest()).map_or(0, |p| p.piece_type.cp_value()),
+    }
+}
+
+/// The transposition table's move first, then captures by most valuable victim/least valuable attacker
+fn order_score(board: &Board, mov: &Move, tt_move: Option<Move>) -> i32 {
+    if Some(*mov) == tt_move {
+        return INF
+    }
+    let attacker = mov.piece().piece_type.cp_value();
+    match mov.move_meta() {
+        MoveMeta::Capture | MoveMeta::Enpassant => capture_value(board, mov)*10 - attacker,
+        MoveMeta::Promotion{ is_capture, piece_type } => {
+            let victim = if is_capture { capture_value(board, mov) } else { 0 };
+            (victim + piece_type.cp_value())*10 - attacker
+        },
+        _ => 0,
+    }
+}
@@ -0,0 +1,152 @@
+use crate::moves::Move;
+
+/// Slots per bucket. A key can be stored in any slot of the bucket its hash indexes
+pub const BUCKET_SLOTS: usize = 4;
+
+/// Something stored in a `Table`
+pub trait TableEntry: Copy + Default {
+    /// How much the entry is worth keeping when its bucket is full.
+    /// The entry with the lowest priority is replaced. {age} is the table's current age
+    fn priority(&self, age: u8) -> i32;
+}
+
+#[derive(Copy, Clone, Default)]
+struct Slot<T> {
+    key: u64,
+    // every key can be stored, including 0, so empty slots are marked separately
+    used: bool,
+    entry: T,
+}
+
+/// Fixed size hash table keyed by position hashes, with a power of two number of buckets
+pub struct Table<T: TableEntry> {
+    buckets: Vec<[Slot<T>; BUCKET_SLOTS]>,
+    age: u8,
+}
+
+impl<T: TableEntry> Table<T> {
+    /// Largest table that fits in {mb} megabytes. Holds at least one bucket
+    pub fn new(mb: usize) -> Self {
+        let bucket_size = std::mem::size_of::<[Slot<T>; BUCKET_SLOTS]>();
+        let max_buckets = (mb * 1024 * 1024 / bucket_size).max(1);
+        // round down to a power of two so the bucket index is a mask of the key
+        let n_buckets = 1 << (63 - (max_buckets as u64).leading_zeros());
+        Table {
+            buckets: vec![[Slot::default(); BUCKET_SLOTS]; n_buckets],
+            age: 0,
+        }
+    }
+
+    /// Number of entries the table can hold
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SLOTS
+    }
+
+    pub fn age(&self) -> u8 {
+        self.age
+    }
+
+    /// Mark the following stores as newer than the current entries
+    pub fn new_age(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            *bucket = [Slot::default(); BUCKET_SLOTS];
+        }
+        self.age = 0;
+    }
+
+    fn bucket_i(&self, key: u64) -> usize {
+        key as usize & (self.buckets.len() - 1)
+    }
+
+    pub fn probe(&self, key: u64) -> Option<T> {
+        self.buckets[self.bucket_i(key)].iter()
+                .find(|slot| slot.used && slot.key == key)
+                .map(|slot| slot.entry)
+    }
+
+    /// Store {entry} for {key}, over the key's previous entry or else the lowest priority slot
+    pub fn store(&mut self, key: u64, entry: T) {
+        let age = self.age;
+        let bucket_i = self.bucket_i(key);
+        let bucket = &mut self.buckets[bucket_i];
+        let slot_i = bucket.iter()
+                .position(|slot| !slot.used || slot.key == key)
+                .unwrap_or_else(|| {
+                    (0..BUCKET_SLOTS).min_by_key(|&i| bucket[i].entry.priority(age)).unwrap()
+                });
+        bucket[slot_i] = Slot{ key, used: true, entry };
+    }
+
+    /// Permille of the first 1000 slots in use, for UCI hashfull
+    pub fn hashfull(&self) -> usize {
+        let slots = self.buckets.iter().flat_map(|bucket| bucket.iter()).take(1000);
+        let (mut used, mut total) = (0, 0);
+        for slot in slots {
+            total += 1;
+            if slot.used {
+                used += 1;
+            }
+        }
+        used * 1000 / total
+    }
+}
+
+/// Which side of the search window the stored score is on
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// Failed high, the score is at least this
+    Lower,
+    /// Failed low, the score is at most this
+    Upper,
+}
+
+/// A searched position
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TTEntry {
+    pub best_move: Move,
+    pub score: i16,
+    pub depth: u8,
+    pub bound: Bound,
+    pub age: u8,
+}
+
+impl Default for TTEntry {
+    fn default() -> Self {
+        TTEntry {
+            best_move: Move::invalid(),
+            score: 0,
+            depth: 0,
+            bound: Bound::Upper,
+            age: 0,
+        }
+    }
+}
+
+impl TableEntry for TTEntry {
+    // deep entries from the current search are worth the most
+    fn priority(&self, age: u8) -> i32 {
+        self.depth as i32 - 8 * age.wrapping_sub(self.age) as i32
+    }
+}
+
+/// Transposition table for search
+pub type TransTable = Table<TTEntry>;
+
+/// Node count of a perft subtree
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct PerftEntry {
+    pub depth: u16,
+    pub nodes: u64,
+}
+
+impl TableEntry for PerftEntry {
+    fn priority(&self, _age: u8) -> i32 {
+        self.depth as i32
+    }
+}
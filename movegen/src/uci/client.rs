@@ -173,11 +173,11 @@ impl UCIClient {
                         if let Err(e) = tx.send(
                             Message::from_str(&line).unwrap_or(Message::Invalid) 
                         ) {
-                            eprintln!("send message error: {}", e);
+                            warn!("send message error: {}", e);
                         }
                     }
                     Err(e) => {
-                        eprintln!("read line error: {}", e);
+                        warn!("read line error: {}", e);
                     }
                 }
             }
@@ -188,6 +188,7 @@ impl UCIClient {
     pub fn set_option(
         &mut self, option: UCIOption, value: UCIOptionType
     ) -> Result<(), Box<dyn Error>> {
+        debug!("setoption {} {}", option.name, value.to_str());
         Ok (self._in()?.write_all(
             format!(
                 "setoption name {} value {}\n", 
@@ -205,7 +206,7 @@ impl UCIClient {
         // self.clear_out()?;
         self._in()?.write_all(b"uci")?;
         while let Ok(message) = self.out_reader.recv_timeout(Duration::from_millis(500)) {
-            println!("received msg: {:?}", message);
+            debug!("received msg: {:?}", message);
             if let Message::UciOk = message {
                 return Ok( true )
             }
@@ -0,0 +1,276 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::board::Board;
+use crate::eval::{Evaluator, EvalParams};
+use crate::game::Game;
+use crate::search::{Searcher, SearchLimits, SearchResult};
+use crate::tt::TransTable;
+
+pub const ENGINE_NAME: &str = "Geistchess";
+pub const ENGINE_AUTHOR: &str = "geist";
+
+pub const HASH_MB_DEFAULT: usize = 16;
+pub const HASH_MB_MAX: usize = 4096;
+
+const MOVE_OVERHEAD_MS: u64 = 50;
+
+/// Limits sent with the go command. Times are in milliseconds
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct GoParams {
+    pub depth: Option<u16>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<u64>,
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u64>,
+    pub infinite: bool,
+}
+
+impl GoParams {
+    pub fn from_tokens<'a>(tokens: impl Iterator<Item=&'a str>) -> GoParams {
+        let mut params = GoParams::default();
+        let mut tokens = tokens.peekable();
+        while let Some(token) = tokens.next() {
+            let mut value = || tokens.peek().and_then(|v| v.parse::<u64>().ok());
+            match token {
+                "depth"     => params.depth = value().map(|v| v as u16),
+                "nodes"     => params.nodes = value(),
+                "movetime"  => params.movetime = value(),
+                "wtime"     => params.wtime = value(),
+                "btime"     => params.btime = value(),
+                "winc"      => params.winc = value(),
+                "binc"      => params.binc = value(),
+                "movestogo" => params.movestogo = value(),
+                "infinite"  => params.infinite = true,
+                _ => {}
+            }
+        }
+        params
+    }
+
+    /// Search limits for {player}. With a clock, spend an even share of the remaining time
+    /// over the moves to go (or 30 more moves) plus most of the increment
+    pub fn search_limits(&self, player: bool) -> SearchLimits {
+        let mut limits = SearchLimits{ depth: self.depth, nodes: self.nodes, time: None };
+        if self.infinite {
+            return limits
+        }
+        let (time, inc) = if player { (self.wtime, self.winc) } else { (self.btime, self.binc) };
+        let move_time = self.movetime.or_else(|| time.map(|time| {
+            let share = time / self.movestogo.unwrap_or(30).max(1) + inc.unwrap_or(0) * 3/4;
+            // keep a margin for communication overhead
+            share.min(time.saturating_sub(MOVE_OVERHEAD_MS)).max(1)
+        }));
+        limits.time = move_time.map(Duration::from_millis);
+        limits
+    }
+}
+
+/// UCI info line for a completed search iteration
+fn info_str(result: &SearchResult) -> String {
+    let score = match result.mate_in() {
+        Some(n) => format!("mate {}", n),
+        None => format!("cp {}", result.score),
+    };
+    let millis = result.time.as_millis() as u64;
+    let mut info = format!(
+        "info depth {} score {} nodes {} time {} nps {}",
+        result.depth, score, result.nodes, millis, result.nodes * 1000 / millis.max(1)
+    );
+    if !result.pv.is_empty() {
+        let pv: Vec<String> = result.pv.iter().map(|mov| mov.to_uci()).collect();
+        info.push_str(&format!(" pv {}", pv.join(" ")));
+    }
+    info
+}
+
+/// The engine side of the UCI protocol. Reads GUI commands through `UCIEngine::handle`
+/// and writes responses to {out}. Searches run on their own thread so stop/isready stay responsive
+pub struct UCIEngine<W: Write + Send + 'static> {
+    game: Game,
+    out: Arc<Mutex<W>>,
+    stop: Arc<AtomicBool>,
+    // the search thread hands the table back when it finishes
+    search_thread: Option<JoinHandle<TransTable>>,
+    tt: Option<TransTable>,
+    hash_mb: usize,
+    // weights from the EvalFile option
+    eval_params: EvalParams,
+}
+
+impl<W: Write + Send + 'static> UCIEngine<W> {
+    pub fn new(out: W) -> Self {
+        UCIEngine {
+            game: Game::standard(),
+            out: Arc::new(Mutex::new(out)),
+            stop: Arc::new(AtomicBool::new(false)),
+            search_thread: None,
+            tt: Some( TransTable::new(HASH_MB_DEFAULT) ),
+            hash_mb: HASH_MB_DEFAULT,
+            eval_params: EvalParams::default(),
+        }
+    }
+
+    /// The position to search from
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn hash_mb(&self) -> usize {
+        self.hash_mb
+    }
+
+    pub fn eval_params(&self) -> &EvalParams {
+        &self.eval_params
+    }
+
+    /// Handle one line of input. Returns false once the GUI sends quit
+    pub fn handle(&mut self, line: &str) -> bool {
+        debug!("<- {}", line);
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                self.send(&format!("id name {}", ENGINE_NAME));
+                self.send(&format!("id author {}", ENGINE_AUTHOR));
+                self.send(&format!(
+                    "option name Hash type spin default {} min 1 max {}", HASH_MB_DEFAULT, HASH_MB_MAX
+                ));
+                self.send("option name EvalFile type string default <empty>");
+                self.send("uciok");
+            },
+            Some("isready") => self.send("readyok"),
+            Some("ucinewgame") => {
+                self.stop_search();
+                self.game = Game::standard();
+                if let Some(tt) = self.tt.as_mut() {
+                    tt.clear();
+                }
+            },
+            Some("setoption") => {
+                self.stop_search();
+                self.set_option(tokens);
+            },
+            Some("position") => {
+                self.stop_search();
+                if let Err(e) = self.set_position(tokens) {
+                    self.send(&format!("info string {}", e));
+                }
+            },
+            Some("go") => {
+                self.stop_search();
+                self.go(GoParams::from_tokens(tokens));
+            },
+            Some("stop") => self.stop_search(),
+            Some("quit") => {
+                self.stop_search();
+                return false
+            },
+            Some(cmd) => self.send(&format!("info string unknown command {}", cmd)),
+            None => {},
+        }
+        true
+    }
+
+    fn send(&self, msg: &str) {
+        send(&self.out, msg)
+    }
+
+    // setoption name <id> [value <x>]
+    fn set_option<'a>(&mut self, tokens: impl Iterator<Item=&'a str>) {
+        let tokens: Vec<&str> = tokens.collect();
+        let value_i = tokens.iter().position(|t| *t == "value").unwrap_or(tokens.len());
+        let name = tokens.get(1..value_i).unwrap_or(&[]).join(" ");
+        let value = tokens.get(value_i+1..).unwrap_or(&[]).join(" ");
+        match name.to_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(mb) if (1..=HASH_MB_MAX).contains(&mb) => {
+                    self.hash_mb = mb;
+                    self.tt = Some( TransTable::new(mb) );
+                },
+                _ => self.send(&format!("info string invalid Hash value {}", value)),
+            },
+            // a params file written by the tuner, or <empty> for the built in weights
+            "evalfile" => match value.as_str() {
+                "" | "<empty>" => self.eval_params = EvalParams::default(),
+                path => match EvalParams::load(path) {
+                    Ok(params) => self.eval_params = params,
+                    Err(e) => self.send(&format!("info string can't load EvalFile {}: {}", path, e)),
+                },
+            },
+            _ => self.send(&format!("info string unknown option {}", name)),
+        }
+    }
+
+    // position [fen <fenstring> | startpos] moves <move1> ... <movei>
+    fn set_position<'a>(&mut self, tokens: impl Iterator<Item=&'a str>) -> Result<(), Box<dyn std::error::Error>> {
+        let tokens: Vec<&str> = tokens.collect();
+        let moves_i = tokens.iter().position(|t| *t == "moves").unwrap_or(tokens.len());
+        let board = match tokens.first() {
+            Some(&"startpos") => Board::standard(),
+            Some(&"fen") => Board::from_fenstr(&tokens[1..moves_i].join(" "))?,
+            _ => Err( "expected startpos or fen" )?,
+        };
+        let mut game = Game::new(board);
+        for move_str in tokens.iter().skip(moves_i + 1) {
+            let mov = game.board().parse_uci_move(move_str)?;
+            game.make_move(&mov).ok_or("illegal move")?;
+        }
+        self.game = game;
+        Ok( () )
+    }
+
+    fn go(&mut self, params: GoParams) {
+        let board = *self.game.board();
+        let history = self.game.history().to_vec();
+        let limits = params.search_limits(board.player);
+        let infinite = params.infinite;
+        let tt = self.tt.take().unwrap_or_else(|| TransTable::new(self.hash_mb));
+        let eval_params = self.eval_params.clone();
+        let out = Arc::clone(&self.out);
+        let stop = Arc::clone(&self.stop);
+        self.stop.store(false, Ordering::SeqCst);
+        self.search_thread = Some( std::thread::spawn(move || {
+            let mut searcher = Searcher::new(limits, Arc::clone(&stop), tt);
+            searcher.set_evaluator(Evaluator::new(eval_params));
+            let result = searcher.search(&board, &history, |result| send(&out, &info_str(result)));
+            // go infinite only reports its move after stop
+            while infinite && !stop.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            match result.best_move {
+                Some(mov) => send(&out, &format!("bestmove {}", mov.to_uci())),
+                None => send(&out, "bestmove 0000"),
+            }
+            searcher.into_table()
+        }) );
+    }
+
+    /// Signal a running search to stop and wait for its bestmove
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.search_thread.take() {
+            // a panicked search loses its table
+            self.tt = handle.join().ok();
+        }
+    }
+}
+
+impl<W: Write + Send + 'static> Drop for UCIEngine<W> {
+    fn drop(&mut self) {
+        self.stop_search();
+    }
+}
+
+fn send<W: Write>(out: &Mutex<W>, msg: &str) {
+    debug!("-> {}", msg);
+    if let Ok(mut out) = out.lock() {
+        let _ = writeln!(out, "{}", msg);
+        let _ = out.flush();
+    }
+}
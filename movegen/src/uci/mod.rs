@@ -1,3 +1,5 @@
 mod client;
+mod engine;
 
-pub use client::UCIClient;
\ No newline at end of file
+pub use client::UCIClient;
+pub use engine::{UCIEngine, GoParams};
@@ -13,6 +13,7 @@ use crate::pieces::{WHITE, BLACK};
 use crate::moves::Move;
 
 pub const ONES: BitBoard = 0xff_ff_ff_ff_ff_ff_ff_ff;
+pub const DARK_SQUARES: BitBoard = 0xaa_55_aa_55_aa_55_aa_55;
 
 static FILES: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
 
@@ -95,6 +96,22 @@ pub fn rook_castle(player: bool, is_short: bool) -> BitBoard {
     }
 }
 
+pub fn king_home(player: bool) -> BitBoard {
+    match player {
+        WHITE => 0x10,
+        BLACK => 0x10_00_00_00_00_00_00_00,
+    }
+}
+
+pub fn rook_home(player: bool, is_short: bool) -> BitBoard {
+    match (player, is_short) {
+        (WHITE, true)  => 0x80,
+        (WHITE, false) => 0x01,
+        (BLACK, true)  => 0x80_00_00_00_00_00_00_00,
+        (BLACK, false) => 0x01_00_00_00_00_00_00_00,
+    }
+}
+
 pub fn castle_travel_squares(player: bool, is_short: bool) -> BitBoard {
     match (player, is_short) {
         (WHITE, true)  => 0x70,    // 0000 1110 src and dest set
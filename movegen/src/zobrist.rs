@@ -0,0 +1,102 @@
+use crate::board::{Board, Square, Direction};
+use crate::pieces::{PieceType, WHITE, BLACK};
+use crate::pieces::std_pieces::*;
+use crate::moves::BitPositions;
+use crate::utils;
+
+// Key layout: 12*64 piece-square keys, side to move, 4 castling rights, 8 enpassant files
+const PIECE_KEYS_I:  usize = 0;
+const SIDE_KEY_I:    usize = STD_PIECECOUNT*64;
+const CASTLE_KEYS_I: usize = SIDE_KEY_I + 1;
+const ENP_KEYS_I:    usize = CASTLE_KEYS_I + 4;
+const KEY_COUNT:     usize = ENP_KEYS_I + 8;
+
+const SEED: u64 = 0x6765_6973_7463_6865;   // "geistche"
+
+static KEYS: [u64; KEY_COUNT] = gen_keys();
+
+/// Fill the key table with splitmix64 outputs, so the keys are the same on every build
+const fn gen_keys() -> [u64; KEY_COUNT] {
+    let mut keys = [0; KEY_COUNT];
+    let mut state = SEED;
+    let mut i = 0;
+    while i < KEY_COUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+/// Key for the piece at index {piece_i} of `Board::bitboards` standing on {pos}
+pub fn piece_key(piece_i: usize, pos: Square) -> u64 {
+    KEYS[PIECE_KEYS_I + piece_i*64 + pos as usize]
+}
+
+/// Key xored in when black is to move
+pub fn side_key() -> u64 {
+    KEYS[SIDE_KEY_I]
+}
+
+pub fn castle_key(player: bool, is_short: bool) -> u64 {
+    KEYS[CASTLE_KEYS_I + (!player as usize)*2 + (!is_short as usize)]
+}
+
+/// Key of the enpassant file. Only hashed when a pawn of the player to move
+/// stands next to the double pushed pawn, so positions that can't enpassant
+/// capture hash the same whatever their last move was
+pub fn enp_key(board: &Board) -> u64 {
+    if board.enp_target == 0 {
+        return 0
+    }
+    let (file, _) = utils::file_rank(board.enp_target);
+    let pushed_mask = utils::pos_mask(board.enp_target);
+    let neighbours = utils::slide(pushed_mask, 1, &Direction::E) | utils::slide(pushed_mask, 1, &Direction::W);
+    if neighbours & board.piece_bb(PieceType::Pawn, board.player) > 0 {
+        KEYS[ENP_KEYS_I + file as usize]
+    } else {
+        0
+    }
+}
+
+/// Key of all the castling rights held on {board}
+pub fn castle_rights_key(board: &Board) -> u64 {
+    let mut key = 0;
+    for &player in &[WHITE, BLACK] {
+        for &is_short in &[true, false] {
+            if board.castle_rights(player, is_short) {
+                key ^= castle_key(player, is_short);
+            }
+        }
+    }
+    key
+}
+
+/// Hash of the pawns alone, for caching pawn structure evaluations
+pub fn pawn_hash(board: &Board) -> u64 {
+    let mut key = 0;
+    for &player in &[WHITE, BLACK] {
+        let piece_i = match_piece_i(PieceType::Pawn, player);
+        for pos in BitPositions(board.bitboards[piece_i]) {
+            key ^= piece_key(piece_i, pos);
+        }
+    }
+    key
+}
+
+/// Hash of {board} computed from scratch
+pub fn hash(board: &Board) -> u64 {
+    let mut key = 0;
+    for (i, bb) in board.bitboards.iter().enumerate() {
+        for pos in BitPositions(*bb) {
+            key ^= piece_key(i, pos);
+        }
+    }
+    if !board.player {
+        key ^= side_key();
+    }
+    key ^ castle_rights_key(board) ^ enp_key(board)
+}